@@ -0,0 +1,99 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use serde_json::value::RawValue;
+use serde_json::Value;
+
+use refuel_radar_transform::process_feed;
+use refuel_radar_transform::station_struts::StationPrices;
+
+/// Roughly the number of stations in a national feed
+const STATIONS: usize = 8_000;
+
+/// Builds a feed of `count` stations, with every tenth one invalid
+fn synthetic_feed(count: usize) -> String {
+    let brands = ["bp", "Esso", "SHELL", "Tesco", "asda", "Morrisons"];
+    let stations: Vec<String> = (0..count)
+        .map(|i| {
+            let brand = if i % 10 == 9 {
+                "null".to_string()
+            } else {
+                format!("{:?}", brands[i % brands.len()])
+            };
+            format!(
+                r#"{{"site_id":"site-{i}","brand":{brand},"address":"{i} High Street, Town","postcode":"AB{} {}CD","location":{{"latitude":"{}","longitude":{}}},"prices":{{"E5":{},"E10":"{}","B7":{{"price":{},"last_updated":"27/11/2024 09:00:00"}},"SDV":{}}}}}"#,
+                i % 99 + 1,
+                i % 9 + 1,
+                50.0 + (i % 1000) as f64 / 100.0,
+                -4.0 + (i % 600) as f64 / 100.0,
+                149.9 + (i % 7) as f64,
+                138.9 + (i % 5) as f64,
+                145.9 + (i % 3) as f64,
+                159.9 + (i % 11) as f64,
+            )
+        })
+        .collect();
+    format!(
+        r#"{{"last_updated":"27/11/2024 11:45:32","stations":[{}]}}"#,
+        stations.join(",")
+    )
+}
+
+/// Station deserialization as it was before stations were kept as unparsed
+/// slices: parse to `Value`, serialize the array back, and parse it again
+fn reparse_stations(feed: &str) -> usize {
+    let mut document: Value = serde_json::from_str(feed).unwrap();
+    let stations = document["stations"].take();
+    let stations_json = serde_json::to_string(&stations).unwrap();
+    serde_json::from_str::<Vec<Value>>(&stations_json)
+        .unwrap()
+        .into_iter()
+        .filter_map(|value| serde_json::from_value::<StationPrices>(value).ok())
+        .count()
+}
+
+/// Station deserialization as the transform now does it: each element once
+fn single_pass_stations(feed: &str) -> usize {
+    #[derive(serde::Deserialize)]
+    struct Feed<'a> {
+        #[serde(borrow)]
+        stations: Vec<&'a RawValue>,
+    }
+
+    let document: Feed = serde_json::from_str(feed).unwrap();
+    document
+        .stations
+        .into_iter()
+        .filter_map(|raw| serde_json::from_str::<StationPrices>(raw.get()).ok())
+        .count()
+}
+
+fn bench_stations(c: &mut Criterion) {
+    let feed = synthetic_feed(STATIONS);
+    assert_eq!(reparse_stations(&feed), single_pass_stations(&feed));
+
+    let mut group = c.benchmark_group("stations");
+    group.throughput(Throughput::Bytes(feed.len() as u64));
+    group.bench_with_input(BenchmarkId::new("reparse", STATIONS), &feed, |b, feed| {
+        b.iter(|| reparse_stations(black_box(feed)))
+    });
+    group.bench_with_input(
+        BenchmarkId::new("single_pass", STATIONS),
+        &feed,
+        |b, feed| b.iter(|| single_pass_stations(black_box(feed))),
+    );
+    group.finish();
+}
+
+fn bench_process_feed(c: &mut Criterion) {
+    let feed = synthetic_feed(STATIONS);
+
+    let mut group = c.benchmark_group("process_feed");
+    group.throughput(Throughput::Bytes(feed.len() as u64));
+    group.sample_size(20);
+    group.bench_with_input(BenchmarkId::from_parameter(STATIONS), &feed, |b, feed| {
+        b.iter(|| process_feed(black_box(feed)).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_stations, bench_process_feed);
+criterion_main!(benches);
@@ -0,0 +1,461 @@
+//! Command-line front end for transforming retailer feed files.
+//!
+//! Reads one or more feed files (or stdin), runs the transform and writes the
+//! resulting JSON to a file or stdout. Multiple feeds are combined into one
+//! dataset, keeping the most recently updated copy of any duplicated station.
+//! With `--stream`, each feed is instead transformed as it is read and its
+//! stations written straight out, without combining.
+//!
+//! # Exit Codes
+//!
+//! - `0`: every station of every feed was transformed
+//! - `1`: usage or I/O error
+//! - `2`: a feed could not be transformed (bad input)
+//! - `3`: output was written, but some stations were rejected
+
+use std::fs;
+use std::io::{self, Read, Write};
+use std::process::ExitCode;
+
+use serde::Serialize;
+
+use refuel_radar_transform::compact::{to_compact, CompactOptions};
+use refuel_radar_transform::csv::{write_csv, CsvMode};
+use refuel_radar_transform::geojson::{to_geojson, GeoJsonOptions};
+use refuel_radar_transform::station_struts::StationPriceLastUpdated;
+use refuel_radar_transform::stream::{stream_feed_with, JsonArrayWriter};
+use refuel_radar_transform::{process_feeds_with, TransformOptions};
+
+const USAGE: &str = "\
+Usage: refuel-radar-transform [OPTIONS] [FILE]...
+
+Transforms retailer fuel price feeds. Reads stdin when no FILE (or `-`) is given.
+
+Options:
+  -o, --output <FILE>    Write output to FILE instead of stdout
+  -f, --format <FORMAT>  Output format: json (default), short (the short-key
+                         compact profile), geojson or csv
+      --history          Include full price history in GeoJSON properties
+      --latest           Write only the latest prices of each station to CSV
+      --bng              Add British National Grid eastings and northings to
+                         station locations (json, short and geojson formats)
+      --regions          Add each station's postcode area, ONS region and
+                         country (json, short and geojson formats)
+      --stream           Write each station as soon as it is read, without
+                         combining feeds (json and short formats only)
+  -p, --pretty           Pretty-print the output JSON
+  -c, --compact          Write the output JSON without whitespace (default);
+                         this does not select the short format
+  -h, --help             Print this help";
+
+const EXIT_USAGE: u8 = 1;
+const EXIT_BAD_INPUT: u8 = 2;
+const EXIT_PARTIAL: u8 = 3;
+
+/// Output formats the binary can write
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+enum Format {
+    #[default]
+    Json,
+    /// The short-key compact profile
+    Short,
+    GeoJson,
+    Csv,
+}
+
+/// Parsed command-line arguments
+#[derive(Debug, Default)]
+struct Args {
+    inputs: Vec<String>,
+    output: Option<String>,
+    format: Format,
+    history: bool,
+    latest: bool,
+    bng: bool,
+    regions: bool,
+    pretty: bool,
+    stream: bool,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Option<Args>, String> {
+    let mut parsed = Args::default();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "-p" | "--pretty" => parsed.pretty = true,
+            "-c" | "--compact" => parsed.pretty = false,
+            "-o" | "--output" => {
+                let path = args.next().ok_or(format!("{arg} requires a file name"))?;
+                parsed.output = Some(path);
+            }
+            "-f" | "--format" => {
+                let format = args.next().ok_or(format!("{arg} requires a format"))?;
+                parsed.format = match format.as_str() {
+                    "json" => Format::Json,
+                    "short" => Format::Short,
+                    "geojson" => Format::GeoJson,
+                    "csv" => Format::Csv,
+                    other => return Err(format!("unknown format {other}")),
+                };
+            }
+            "--history" => parsed.history = true,
+            "--latest" => parsed.latest = true,
+            "--bng" => parsed.bng = true,
+            "--regions" => parsed.regions = true,
+            "--stream" => parsed.stream = true,
+            "-" => parsed.inputs.push(arg),
+            flag if flag.starts_with('-') => return Err(format!("unknown option {flag}")),
+            _ => parsed.inputs.push(arg),
+        }
+    }
+
+    if parsed.inputs.is_empty() {
+        parsed.inputs.push("-".to_string());
+    }
+    if parsed.stream && !matches!(parsed.format, Format::Json | Format::Short) {
+        return Err("--stream supports only the json and short formats".to_string());
+    }
+    if parsed.history && parsed.format != Format::GeoJson {
+        return Err("--history supports only the geojson format".to_string());
+    }
+    if parsed.latest && parsed.format != Format::Csv {
+        return Err("--latest supports only the csv format".to_string());
+    }
+    if parsed.format == Format::Csv {
+        if parsed.bng {
+            return Err("--bng does not support the csv format".to_string());
+        }
+        if parsed.regions {
+            return Err("--regions does not support the csv format".to_string());
+        }
+    }
+
+    Ok(Some(parsed))
+}
+
+fn transform_options(args: &Args) -> TransformOptions {
+    TransformOptions {
+        grid_references: args.bng,
+        regions: args.regions,
+        ..TransformOptions::default()
+    }
+}
+
+fn read_input(path: &str) -> io::Result<String> {
+    if path == "-" {
+        let mut buffer = String::new();
+        io::stdin().read_to_string(&mut buffer)?;
+        Ok(buffer)
+    } else {
+        fs::read_to_string(path)
+    }
+}
+
+fn open_input(path: &str) -> io::Result<Box<dyn Read>> {
+    if path == "-" {
+        Ok(Box::new(io::stdin().lock()))
+    } else {
+        Ok(Box::new(fs::File::open(path)?))
+    }
+}
+
+fn to_json<T: Serialize>(value: &T, pretty: bool) -> serde_json::Result<String> {
+    if pretty {
+        serde_json::to_string_pretty(value)
+    } else {
+        serde_json::to_string(value)
+    }
+}
+
+fn write_csv_output(args: &Args, stations: &[StationPriceLastUpdated]) -> io::Result<()> {
+    let mode = if args.latest {
+        CsvMode::LatestOnly
+    } else {
+        CsvMode::History
+    };
+    match &args.output {
+        Some(path) => write_csv(io::BufWriter::new(fs::File::create(path)?), stations, mode),
+        None => write_csv(io::stdout().lock(), stations, mode),
+    }
+}
+
+fn write_output(args: &Args, stations: &[StationPriceLastUpdated]) -> io::Result<()> {
+    let mut json = match args.format {
+        Format::Json => to_json(&stations, args.pretty)?,
+        Format::Short => to_json(
+            &to_compact(stations, &CompactOptions::default()),
+            args.pretty,
+        )?,
+        Format::GeoJson => {
+            let options = GeoJsonOptions {
+                include_history: args.history,
+            };
+            to_json(&to_geojson(stations, &options), args.pretty)?
+        }
+        Format::Csv => return write_csv_output(args, stations),
+    };
+    json.push('\n');
+
+    match &args.output {
+        Some(path) => fs::write(path, json),
+        None => io::stdout().lock().write_all(json.as_bytes()),
+    }
+}
+
+/// Streams every feed into one JSON array, returning the number of rejected
+/// stations and whether any feed failed
+fn stream_feeds<W: Write>(
+    args: &Args,
+    inputs: Vec<Box<dyn Read>>,
+    writer: W,
+) -> io::Result<(usize, bool)> {
+    let mut output = JsonArrayWriter::new(writer, args.pretty);
+    let compact_options = CompactOptions::default();
+    let mut rejected = 0;
+    let mut failed = false;
+
+    for (path, input) in args.inputs.iter().zip(inputs) {
+        let mut stream = stream_feed_with(input, transform_options(args));
+        for station in stream.by_ref() {
+            match station {
+                Ok(station) if args.format == Format::Short => {
+                    for compact in to_compact(std::slice::from_ref(&station), &compact_options) {
+                        output.write(&compact)?;
+                    }
+                }
+                Ok(station) => output.write(&station)?,
+                Err(e) => {
+                    eprintln!("error: {path}: {e}");
+                    failed = true;
+                }
+            }
+        }
+
+        let report = stream.into_report();
+        for (reason, count) in &report.rejection_counts {
+            eprintln!("{path}: rejected {count} station(s): {reason}");
+        }
+        rejected += report.rejections.len();
+    }
+
+    let mut writer = output.finish()?;
+    writer.write_all(b"\n")?;
+    writer.flush()?;
+    Ok((rejected, failed))
+}
+
+fn stream_output(args: &Args) -> ExitCode {
+    let mut inputs = Vec::with_capacity(args.inputs.len());
+    for path in &args.inputs {
+        match open_input(path) {
+            Ok(input) => inputs.push(input),
+            Err(e) => {
+                eprintln!("error: cannot read {path}: {e}");
+                return ExitCode::from(EXIT_USAGE);
+            }
+        }
+    }
+
+    let result = match &args.output {
+        Some(path) => fs::File::create(path)
+            .and_then(|file| stream_feeds(args, inputs, io::BufWriter::new(file))),
+        None => stream_feeds(args, inputs, io::BufWriter::new(io::stdout().lock())),
+    };
+    match result {
+        Err(e) => {
+            eprintln!("error: cannot write output: {e}");
+            ExitCode::from(EXIT_USAGE)
+        }
+        Ok((rejected, failed)) => exit_code(failed, rejected),
+    }
+}
+
+/// Chooses the exit code for a run whose feeds were all read: `2` if any
+/// feed could not be transformed, else `3` if any station was rejected
+fn exit_code(failed: bool, rejected: usize) -> ExitCode {
+    if failed {
+        ExitCode::from(EXIT_BAD_INPUT)
+    } else if rejected > 0 {
+        ExitCode::from(EXIT_PARTIAL)
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("error: {e}\n\n{USAGE}");
+            return ExitCode::from(EXIT_USAGE);
+        }
+    };
+
+    if args.stream {
+        return stream_output(&args);
+    }
+
+    let mut feeds = Vec::with_capacity(args.inputs.len());
+    for path in &args.inputs {
+        match read_input(path) {
+            Ok(json_data) => feeds.push(json_data),
+            Err(e) => {
+                eprintln!("error: cannot read {path}: {e}");
+                return ExitCode::from(EXIT_USAGE);
+            }
+        }
+    }
+
+    let combined = process_feeds_with(feeds.iter().map(String::as_str), &transform_options(&args));
+    let mut rejected = 0;
+
+    for (path, feed) in args.inputs.iter().zip(&combined.feeds) {
+        match feed {
+            Ok(report) => {
+                for (reason, count) in &report.rejection_counts {
+                    eprintln!("{path}: rejected {count} station(s): {reason}");
+                }
+                rejected += report.rejections.len();
+            }
+            Err(e) => eprintln!("error: {path}: {e}"),
+        }
+    }
+    if combined.has_failed_feeds() {
+        return exit_code(true, rejected);
+    }
+
+    for conflict in &combined.conflicts {
+        eprintln!(
+            "{}: kept {} ({}), discarded {} older occurrence(s)",
+            conflict.site_id,
+            args.inputs[conflict.kept.feed],
+            conflict.kept.lu,
+            conflict.discarded.len()
+        );
+    }
+
+    if let Err(e) = write_output(&args, &combined.stations) {
+        eprintln!("error: cannot write output: {e}");
+        return ExitCode::from(EXIT_USAGE);
+    }
+
+    exit_code(false, rejected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Option<Args>, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    const FEED: &str = r#"{"last_updated": "27/11/2024 11:45:32", "stations": [
+        {"site_id": "a", "brand": "bp", "address": "", "postcode": "",
+         "location": {"latitude": 51.5, "longitude": -0.1}, "prices": {"E10": 138.9}},
+        {"site_id": "b", "brand": null}
+    ]}"#;
+
+    #[test]
+    fn test_parse_args() {
+        let args = parse(&[
+            "-f",
+            "geojson",
+            "--history",
+            "-p",
+            "-o",
+            "out.json",
+            "a.json",
+            "-",
+        ])
+        .unwrap()
+        .unwrap();
+        assert_eq!(args.format, Format::GeoJson);
+        assert!(args.history && args.pretty && !args.stream);
+        assert_eq!(args.output.as_deref(), Some("out.json"));
+        assert_eq!(args.inputs, ["a.json", "-"]);
+
+        let args = parse(&["--stream", "-f", "short"]).unwrap().unwrap();
+        assert!(args.stream);
+        assert_eq!(args.inputs, ["-"]);
+        assert!(parse(&["-p", "-c"])
+            .unwrap()
+            .is_some_and(|args| !args.pretty));
+        let args = parse(&["--latest", "-f", "csv"]).unwrap().unwrap();
+        assert!(args.latest && !args.history);
+        let args = parse(&["--bng", "--regions"]).unwrap().unwrap();
+        assert!(args.bng && args.regions);
+        assert!(parse(&["a.json", "--help"]).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_parse_args_errors() {
+        assert_eq!(parse(&["-f", "xml"]).unwrap_err(), "unknown format xml");
+        assert_eq!(
+            parse(&["-f", "compact"]).unwrap_err(),
+            "unknown format compact"
+        );
+        assert_eq!(parse(&["-f"]).unwrap_err(), "-f requires a format");
+        assert_eq!(
+            parse(&["--output"]).unwrap_err(),
+            "--output requires a file name"
+        );
+        assert_eq!(
+            parse(&["--verbose"]).unwrap_err(),
+            "unknown option --verbose"
+        );
+        for format in ["csv", "geojson"] {
+            assert_eq!(
+                parse(&["--stream", "-f", format]).unwrap_err(),
+                "--stream supports only the json and short formats"
+            );
+        }
+        for format in ["json", "short", "csv"] {
+            assert_eq!(
+                parse(&["--history", "-f", format]).unwrap_err(),
+                "--history supports only the geojson format"
+            );
+        }
+        for format in ["json", "short", "geojson"] {
+            assert_eq!(
+                parse(&["--latest", "-f", format]).unwrap_err(),
+                "--latest supports only the csv format"
+            );
+        }
+        for flag in ["--bng", "--regions"] {
+            assert_eq!(
+                parse(&[flag, "-f", "csv"]).unwrap_err(),
+                format!("{flag} does not support the csv format")
+            );
+        }
+    }
+
+    #[test]
+    fn test_exit_code() {
+        assert_eq!(exit_code(false, 0), ExitCode::SUCCESS);
+        assert_eq!(exit_code(false, 2), ExitCode::from(EXIT_PARTIAL));
+        assert_eq!(exit_code(true, 0), ExitCode::from(EXIT_BAD_INPUT));
+        assert_eq!(exit_code(true, 2), ExitCode::from(EXIT_BAD_INPUT));
+    }
+
+    #[test]
+    fn test_stream_feeds_counts() {
+        let args = parse(&["--stream", "good.json", "bad.json"])
+            .unwrap()
+            .unwrap();
+        let inputs: Vec<Box<dyn Read>> = vec![Box::new(FEED.as_bytes()), Box::new(&b"[]"[..])];
+        let mut output = Vec::new();
+
+        let (rejected, failed) = stream_feeds(&args, inputs, &mut output).unwrap();
+        assert_eq!((rejected, failed), (1, true));
+        assert_eq!(exit_code(failed, rejected), ExitCode::from(EXIT_BAD_INPUT));
+
+        let output: serde_json::Value = serde_json::from_slice(&output).unwrap();
+        assert_eq!(output.as_array().map(Vec::len), Some(1));
+    }
+}
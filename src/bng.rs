@@ -0,0 +1,278 @@
+use serde::{Deserialize, Serialize};
+
+/// A British National Grid (OSGB36) coordinate, in metres
+///
+/// # Accuracy
+///
+/// Conversions to and from WGS84 use the Ordnance Survey's seven-parameter
+/// Helmert transformation between OSGB36 and WGS84 (taken as ETRS89). It is
+/// accurate to about 5 m anywhere in Great Britain, and typically 2–3 m:
+/// plenty to place a fuel station on a map, but not survey grade. The OSTN15
+/// grid transformation, which the OS defines as the exact relationship
+/// between the two (about 0.1 m), is not implemented. Converting a point to
+/// the grid and back reproduces it to within a centimetre.
+///
+/// # Examples
+///
+/// ```rust
+/// use refuel_radar_transform::bng::GridReference;
+///
+/// let grid = GridReference::from_wgs84(51.5007, -0.1246);
+/// let (latitude, longitude) = grid.to_wgs84();
+/// assert!((latitude - 51.5007).abs() < 1e-7 && (longitude + 0.1246).abs() < 1e-7);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GridReference {
+    pub easting: f64,
+    pub northing: f64,
+}
+
+/// An ellipsoid's semi-major and semi-minor axes, in metres
+struct Ellipsoid {
+    a: f64,
+    b: f64,
+}
+
+impl Ellipsoid {
+    /// Eccentricity squared
+    fn e2(&self) -> f64 {
+        1.0 - (self.b * self.b) / (self.a * self.a)
+    }
+}
+
+const AIRY_1830: Ellipsoid = Ellipsoid {
+    a: 6_377_563.396,
+    b: 6_356_256.909,
+};
+const WGS84: Ellipsoid = Ellipsoid {
+    a: 6_378_137.0,
+    b: 6_356_752.314_245,
+};
+
+/// National Grid projection: scale factor, true origin and false origin
+const F0: f64 = 0.999_601_271_7;
+const LATITUDE_0: f64 = 49.0;
+const LONGITUDE_0: f64 = -2.0;
+const EASTING_0: f64 = 400_000.0;
+const NORTHING_0: f64 = -100_000.0;
+
+/// WGS84 to OSGB36 Helmert parameters: translations in metres, scale in
+/// parts per million and rotations in arc seconds
+const HELMERT: Helmert = Helmert {
+    tx: -446.448,
+    ty: 125.157,
+    tz: -542.060,
+    s: 20.4894,
+    rx: -0.1502,
+    ry: -0.2470,
+    rz: -0.8421,
+};
+
+struct Helmert {
+    tx: f64,
+    ty: f64,
+    tz: f64,
+    s: f64,
+    rx: f64,
+    ry: f64,
+    rz: f64,
+}
+
+impl Helmert {
+    fn inverse(&self) -> Helmert {
+        Helmert {
+            tx: -self.tx,
+            ty: -self.ty,
+            tz: -self.tz,
+            s: -self.s,
+            rx: -self.rx,
+            ry: -self.ry,
+            rz: -self.rz,
+        }
+    }
+
+    fn apply(&self, [x, y, z]: [f64; 3]) -> [f64; 3] {
+        let arc_second = (1.0_f64 / 3600.0).to_radians();
+        let (rx, ry, rz) = (
+            self.rx * arc_second,
+            self.ry * arc_second,
+            self.rz * arc_second,
+        );
+        let scale = 1.0 + self.s * 1e-6;
+        [
+            self.tx + scale * x - rz * y + ry * z,
+            self.ty + rz * x + scale * y - rx * z,
+            self.tz - ry * x + rx * y + scale * z,
+        ]
+    }
+}
+
+impl GridReference {
+    /// Converts a WGS84 latitude and longitude, in degrees, to the grid
+    pub fn from_wgs84(latitude: f64, longitude: f64) -> GridReference {
+        let cartesian = to_cartesian(&WGS84, latitude, longitude);
+        let (latitude, longitude) = from_cartesian(&AIRY_1830, HELMERT.apply(cartesian));
+        project(latitude, longitude)
+    }
+
+    /// Converts this grid reference to a WGS84 latitude and longitude, in degrees
+    pub fn to_wgs84(self) -> (f64, f64) {
+        let (latitude, longitude) = unproject(self);
+        let cartesian = to_cartesian(&AIRY_1830, latitude, longitude);
+        from_cartesian(&WGS84, HELMERT.inverse().apply(cartesian))
+    }
+
+    /// Returns `true` if the reference is within the extent of the National
+    /// Grid (0–700 km east, 0–1300 km north)
+    pub fn is_on_grid(&self) -> bool {
+        (0.0..=700_000.0).contains(&self.easting) && (0.0..=1_300_000.0).contains(&self.northing)
+    }
+}
+
+/// Earth-centred cartesian coordinates of a point at zero ellipsoidal height
+fn to_cartesian(ellipsoid: &Ellipsoid, latitude: f64, longitude: f64) -> [f64; 3] {
+    let (phi, lambda) = (latitude.to_radians(), longitude.to_radians());
+    let e2 = ellipsoid.e2();
+    let nu = ellipsoid.a / (1.0 - e2 * phi.sin().powi(2)).sqrt();
+    [
+        nu * phi.cos() * lambda.cos(),
+        nu * phi.cos() * lambda.sin(),
+        (1.0 - e2) * nu * phi.sin(),
+    ]
+}
+
+/// Latitude and longitude, in degrees, of earth-centred cartesian coordinates
+fn from_cartesian(ellipsoid: &Ellipsoid, [x, y, z]: [f64; 3]) -> (f64, f64) {
+    let e2 = ellipsoid.e2();
+    let p = x.hypot(y);
+    let mut phi = z.atan2(p * (1.0 - e2));
+    for _ in 0..10 {
+        let nu = ellipsoid.a / (1.0 - e2 * phi.sin().powi(2)).sqrt();
+        let next = (z + e2 * nu * phi.sin()).atan2(p);
+        let converged = (next - phi).abs() < 1e-12;
+        phi = next;
+        if converged {
+            break;
+        }
+    }
+    (phi.to_degrees(), y.atan2(x).to_degrees())
+}
+
+/// Meridional arc from the true origin's latitude to `phi`
+fn meridional_arc(phi: f64) -> f64 {
+    let Ellipsoid { a, b } = AIRY_1830;
+    let n = (a - b) / (a + b);
+    let (n2, n3) = (n * n, n * n * n);
+    let phi_0 = LATITUDE_0.to_radians();
+    let (dphi, sphi) = (phi - phi_0, phi + phi_0);
+
+    b * F0
+        * ((1.0 + n + 1.25 * n2 + 1.25 * n3) * dphi
+            - (3.0 * n + 3.0 * n2 + 2.625 * n3) * dphi.sin() * sphi.cos()
+            + (1.875 * n2 + 1.875 * n3) * (2.0 * dphi).sin() * (2.0 * sphi).cos()
+            - (35.0 / 24.0) * n3 * (3.0 * dphi).sin() * (3.0 * sphi).cos())
+}
+
+/// Radii of curvature at `phi`: across (nu) and along (rho) the meridian, and
+/// eta squared
+fn curvature(phi: f64) -> (f64, f64, f64) {
+    let e2 = AIRY_1830.e2();
+    let a_f0 = AIRY_1830.a * F0;
+    let denominator = 1.0 - e2 * phi.sin().powi(2);
+    let nu = a_f0 / denominator.sqrt();
+    let rho = a_f0 * (1.0 - e2) / denominator.powf(1.5);
+    (nu, rho, nu / rho - 1.0)
+}
+
+/// Transverse Mercator projection of an OSGB36 latitude and longitude onto the grid
+fn project(latitude: f64, longitude: f64) -> GridReference {
+    let phi = latitude.to_radians();
+    let (nu, rho, eta2) = curvature(phi);
+    let (sin, cos, tan) = (phi.sin(), phi.cos(), phi.tan());
+    let tan2 = tan * tan;
+
+    let i = meridional_arc(phi) + NORTHING_0;
+    let ii = nu / 2.0 * sin * cos;
+    let iii = nu / 24.0 * sin * cos.powi(3) * (5.0 - tan2 + 9.0 * eta2);
+    let iii_a = nu / 720.0 * sin * cos.powi(5) * (61.0 - 58.0 * tan2 + tan2 * tan2);
+    let iv = nu * cos;
+    let v = nu / 6.0 * cos.powi(3) * (nu / rho - tan2);
+    let vi = nu / 120.0
+        * cos.powi(5)
+        * (5.0 - 18.0 * tan2 + tan2 * tan2 + 14.0 * eta2 - 58.0 * tan2 * eta2);
+
+    let dl = (longitude - LONGITUDE_0).to_radians();
+    GridReference {
+        easting: EASTING_0 + iv * dl + v * dl.powi(3) + vi * dl.powi(5),
+        northing: i + ii * dl.powi(2) + iii * dl.powi(4) + iii_a * dl.powi(6),
+    }
+}
+
+/// Inverse Transverse Mercator projection, from the grid to an OSGB36
+/// latitude and longitude
+fn unproject(grid: GridReference) -> (f64, f64) {
+    let a_f0 = AIRY_1830.a * F0;
+    let mut phi = (grid.northing - NORTHING_0) / a_f0 + LATITUDE_0.to_radians();
+    for _ in 0..20 {
+        let remainder = grid.northing - NORTHING_0 - meridional_arc(phi);
+        if remainder.abs() < 1e-5 {
+            break;
+        }
+        phi += remainder / a_f0;
+    }
+
+    let (nu, rho, eta2) = curvature(phi);
+    let (tan, sec) = (phi.tan(), 1.0 / phi.cos());
+    let (tan2, tan4) = (tan * tan, tan.powi(4));
+
+    let vii = tan / (2.0 * rho * nu);
+    let viii = tan / (24.0 * rho * nu.powi(3)) * (5.0 + 3.0 * tan2 + eta2 - 9.0 * tan2 * eta2);
+    let ix = tan / (720.0 * rho * nu.powi(5)) * (61.0 + 90.0 * tan2 + 45.0 * tan4);
+    let x = sec / nu;
+    let xi = sec / (6.0 * nu.powi(3)) * (nu / rho + 2.0 * tan2);
+    let xii = sec / (120.0 * nu.powi(5)) * (5.0 + 28.0 * tan2 + 24.0 * tan4);
+    let xii_a =
+        sec / (5040.0 * nu.powi(7)) * (61.0 + 662.0 * tan2 + 1320.0 * tan4 + 720.0 * tan.powi(6));
+
+    let de = grid.easting - EASTING_0;
+    let latitude = phi - vii * de.powi(2) + viii * de.powi(4) - ix * de.powi(6);
+    let longitude =
+        LONGITUDE_0.to_radians() + x * de - xi * de.powi(3) + xii * de.powi(5) - xii_a * de.powi(7);
+    (latitude.to_degrees(), longitude.to_degrees())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The worked example in the Ordnance Survey's "A guide to coordinate
+    /// systems in Great Britain", Annex C
+    #[test]
+    fn test_projection_matches_ordnance_survey_example() {
+        let latitude = 52.0 + 39.0 / 60.0 + 27.2531 / 3600.0;
+        let longitude = 1.0 + 43.0 / 60.0 + 4.5177 / 3600.0;
+
+        let grid = project(latitude, longitude);
+        assert!((grid.easting - 651_409.903).abs() < 0.001, "{grid:?}");
+        assert!((grid.northing - 313_177.270).abs() < 0.001, "{grid:?}");
+
+        let (phi, lambda) = unproject(grid);
+        assert!((phi - latitude).abs() < 1e-7 && (lambda - longitude).abs() < 1e-7);
+    }
+
+    #[test]
+    fn test_helmert_shift() {
+        // The Airy transit circle at Greenwich is on the OSGB36 prime meridian,
+        // about 100 m east of the WGS84 one
+        let cartesian = to_cartesian(&WGS84, 51.477_928, -0.001_545);
+        let (_, longitude) = from_cartesian(&AIRY_1830, HELMERT.apply(cartesian));
+        assert!(longitude.abs() < 0.0001, "{longitude}");
+
+        for (latitude, longitude) in [(50.066, -5.715), (58.644, -3.070), (54.6, -5.9)] {
+            let grid = GridReference::from_wgs84(latitude, longitude);
+            let (back_latitude, back_longitude) = grid.to_wgs84();
+            assert!((back_latitude - latitude).abs() < 1e-7);
+            assert!((back_longitude - longitude).abs() < 1e-7);
+        }
+    }
+}
@@ -0,0 +1,378 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::OnceLock;
+
+use crate::error::BrandTableError;
+
+/// Words dropped from a brand before fuzzy matching: company suffixes and store formats
+const IGNORED_WORDS: &[&str] = &[
+    "group",
+    "ltd",
+    "limited",
+    "plc",
+    "uk",
+    "connect",
+    "express",
+    "extra",
+    "local",
+    "superstore",
+    "petrol",
+    "fuel",
+    "station",
+    "services",
+];
+
+/// The brands known to the crate, as `(canonical name, raw spellings)`
+const DEFAULT_BRANDS: &[(&str, &[&str])] = &[
+    ("Applegreen", &["applegreen"]),
+    ("ASDA Express", &["asda express"]),
+    ("ASDA", &["asda"]),
+    ("BP", &["bp"]),
+    ("Co Op", &["coop"]),
+    ("Essar", &["essar"]),
+    ("Esso", &["esso"]),
+    ("Gulf", &["gulf"]),
+    ("Harvest Energy", &["harvest energy"]),
+    ("JET", &["jet"]),
+    ("Morrisons", &["morrisons"]),
+    ("Murco", &["murco"]),
+    ("Sainsbury's", &["sainsbury's"]),
+    ("Shell", &["shell"]),
+    ("Tesco", &["tesco"]),
+    ("Texaco", &["texaco"]),
+];
+
+/// Maps raw brand spellings from retailer feeds to one canonical brand name.
+///
+/// # Matching
+///
+/// - Raw brands are trimmed and lowercased before lookup, so aliases are
+///   case-insensitive
+/// - Every canonical name is also an alias of itself
+/// - When fuzzy matching is enabled (the default), brands that miss the exact
+///   aliases are matched again with punctuation, company suffixes and
+///   store-format words removed, and finally by edit distance
+///
+/// # File Format
+///
+/// JSON and TOML tables map each canonical name to its list of aliases:
+///
+/// ```toml
+/// "BP" = ["bp", "b.p."]
+/// "Harvest Energy" = ["harvest energy", "harvest"]
+/// ```
+///
+/// # Examples
+///
+/// ```rust
+/// use refuel_radar_transform::brand::BrandTable;
+///
+/// let table = BrandTable::default().with_brand("Valero", ["valero uk"]);
+/// assert_eq!(table.format("  VALERO UK "), "Valero");
+/// assert_eq!(table.format("bp"), "BP");
+/// assert_eq!(table.format("unknown brand"), "unknown brand");
+/// assert_eq!(table.format("BP Connect"), "BP");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct BrandTable {
+    aliases: HashMap<String, String>,
+    /// Aliases reduced by [`fuzzy_key`], for the fuzzy matching steps
+    fuzzy_aliases: HashMap<String, String>,
+    fuzzy: bool,
+}
+
+/// How a raw brand was matched to its canonical brand
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BrandMatchKind {
+    /// The trimmed, lowercased brand is an alias in the table
+    Exact,
+    /// The brand matched after removing punctuation, suffixes and store-format words
+    Normalised,
+    /// The normalised brand is within this edit distance of exactly one alias
+    EditDistance(usize),
+}
+
+/// The canonical brand for a raw brand, and how it was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BrandMatch<'a> {
+    pub canonical: &'a str,
+    pub kind: BrandMatchKind,
+}
+
+impl BrandTable {
+    /// Creates a table with no brands; every brand will hit the fallback
+    pub fn empty() -> Self {
+        BrandTable {
+            aliases: HashMap::new(),
+            fuzzy_aliases: HashMap::new(),
+            fuzzy: true,
+        }
+    }
+
+    /// Enables or disables fuzzy matching (enabled by default)
+    pub fn with_fuzzy(mut self, fuzzy: bool) -> Self {
+        self.fuzzy = fuzzy;
+        self
+    }
+
+    /// Adds a canonical brand and its aliases, replacing any existing mapping for them
+    pub fn insert<I, S>(&mut self, canonical: &str, aliases: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let aliases = aliases.into_iter();
+        for alias in std::iter::once(canonical.to_string())
+            .chain(aliases.map(|alias| alias.as_ref().to_string()))
+        {
+            self.aliases
+                .insert(alias_key(&alias), canonical.to_string());
+            let fuzzy_alias = fuzzy_key(&alias);
+            if fuzzy_alias.is_empty() {
+                continue;
+            }
+            // An alias that only reduces to this key by dropping words (e.g. "asda express")
+            // must not take it over from a brand that spells it out (e.g. "asda")
+            let dropped_words = alias_key(&alias)
+                .chars()
+                .filter(|c| c.is_alphanumeric())
+                .ne(fuzzy_alias.chars());
+            if dropped_words {
+                self.fuzzy_aliases
+                    .entry(fuzzy_alias)
+                    .or_insert_with(|| canonical.to_string());
+            } else {
+                self.fuzzy_aliases
+                    .insert(fuzzy_alias, canonical.to_string());
+            }
+        }
+    }
+
+    /// Builder-style version of [`BrandTable::insert`]
+    pub fn with_brand<I, S>(mut self, canonical: &str, aliases: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.insert(canonical, aliases);
+        self
+    }
+
+    /// Parses a JSON brand table; see [`BrandTable`] for the format
+    pub fn from_json(json: &str) -> Result<Self, BrandTableError> {
+        Ok(Self::from_entries(serde_json::from_str(json)?))
+    }
+
+    /// Parses a TOML brand table; see [`BrandTable`] for the format
+    pub fn from_toml(toml: &str) -> Result<Self, BrandTableError> {
+        Ok(Self::from_entries(toml::from_str(toml)?))
+    }
+
+    /// Loads a brand table file, reading it as TOML if the extension is `.toml`
+    /// and as JSON otherwise
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, BrandTableError> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Self::from_toml(&contents),
+            _ => Self::from_json(&contents),
+        }
+    }
+
+    fn from_entries(entries: BTreeMap<String, Vec<String>>) -> Self {
+        let mut table = Self::empty();
+        for (canonical, aliases) in &entries {
+            table.insert(canonical, aliases);
+        }
+        table
+    }
+
+    /// Returns the canonical brand for a raw spelling, or `None` if it is not in the table
+    pub fn canonical(&self, raw: &str) -> Option<&str> {
+        self.resolve(raw).map(|brand_match| brand_match.canonical)
+    }
+
+    /// Matches a raw spelling against the table, reporting how it was matched
+    ///
+    /// # Matching Order
+    ///
+    /// 1. Exact (case-insensitive) alias lookup
+    /// 2. Lookup with punctuation, company suffixes and store-format words removed
+    /// 3. Closest alias by edit distance, allowing 1 edit for names of 5 to 8
+    ///    characters and 2 edits for longer names; shorter names and ties never match
+    ///
+    /// Steps 2 and 3 are skipped when fuzzy matching is disabled.
+    pub fn resolve(&self, raw: &str) -> Option<BrandMatch<'_>> {
+        if let Some(canonical) = self.aliases.get(&alias_key(raw)) {
+            return Some(BrandMatch {
+                canonical,
+                kind: BrandMatchKind::Exact,
+            });
+        }
+        if !self.fuzzy {
+            return None;
+        }
+
+        let key = fuzzy_key(raw);
+        if let Some(canonical) = self.fuzzy_aliases.get(&key) {
+            return Some(BrandMatch {
+                canonical,
+                kind: BrandMatchKind::Normalised,
+            });
+        }
+
+        let max_distance = match key.chars().count() {
+            0..=4 => return None,
+            5..=8 => 1,
+            _ => 2,
+        };
+        let mut best: Option<(usize, &str)> = None;
+        let mut tied = false;
+        for (alias, canonical) in &self.fuzzy_aliases {
+            let distance = edit_distance(&key, alias);
+            if distance > max_distance {
+                continue;
+            }
+            match best {
+                Some((best_distance, best_canonical)) if distance == best_distance => {
+                    tied |= best_canonical != canonical;
+                }
+                Some((best_distance, _)) if distance > best_distance => {}
+                _ => {
+                    best = Some((distance, canonical));
+                    tied = false;
+                }
+            }
+        }
+
+        match best {
+            Some((distance, canonical)) if !tied => Some(BrandMatch {
+                canonical,
+                kind: BrandMatchKind::EditDistance(distance),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Returns the canonical brand for a raw spelling, or the trimmed raw brand
+    /// if it is not in the table
+    pub fn format(&self, raw: &str) -> String {
+        self.canonical(raw)
+            .unwrap_or_else(|| raw.trim())
+            .to_string()
+    }
+
+    /// A shared instance of the default table
+    pub(crate) fn shared_default() -> &'static BrandTable {
+        static DEFAULT: OnceLock<BrandTable> = OnceLock::new();
+        DEFAULT.get_or_init(BrandTable::default)
+    }
+}
+
+/// The crate's built-in brand table
+impl Default for BrandTable {
+    fn default() -> Self {
+        let mut table = Self::empty();
+        for (canonical, aliases) in DEFAULT_BRANDS {
+            table.insert(canonical, aliases.iter());
+        }
+        table
+    }
+}
+
+fn alias_key(raw: &str) -> String {
+    raw.trim().to_lowercase()
+}
+
+/// Reduces a brand to lowercase alphanumeric words, without [`IGNORED_WORDS`], joined without spaces
+fn fuzzy_key(raw: &str) -> String {
+    let cleaned: String = raw
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            c if c.is_alphanumeric() => Some(c),
+            c if c.is_whitespace() || c == '/' || c == '&' => Some(' '),
+            _ => None,
+        })
+        .collect();
+
+    let words: Vec<&str> = cleaned.split_whitespace().collect();
+    let kept: Vec<&str> = words
+        .iter()
+        .copied()
+        .filter(|word| !IGNORED_WORDS.contains(word))
+        .collect();
+
+    // A brand made only of ignored words (e.g. "Local") keeps them
+    if kept.is_empty() {
+        words.concat()
+    } else {
+        kept.concat()
+    }
+}
+
+/// Levenshtein distance between two strings, by character
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+
+    for (i, a_char) in a.chars().enumerate() {
+        current[0] = i + 1;
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_table() {
+        let table = BrandTable::default();
+        assert_eq!(table.format("harvest energy"), "Harvest Energy");
+        assert_eq!(table.format(" Co Op"), "Co Op");
+        assert_eq!(table.canonical("Valero"), None);
+    }
+
+    #[test]
+    fn test_fuzzy_matching() {
+        let table = BrandTable::default();
+        assert_eq!(table.format("BP Connect"), "BP");
+        assert_eq!(table.format("Esso Express"), "Esso");
+        assert_eq!(table.format("ASDA Express"), "ASDA Express");
+        assert_eq!(table.format("ASDA Superstore"), "ASDA");
+        assert_eq!(table.format("Co-op"), "Co Op");
+        assert_eq!(table.format("Coop Group"), "Co Op");
+        assert_eq!(table.format("SAINSBURYS"), "Sainsbury's");
+        assert_eq!(
+            table.resolve("Morisons").map(|m| m.kind),
+            Some(BrandMatchKind::EditDistance(1))
+        );
+        assert_eq!(table.canonical("Jot"), None);
+        assert_eq!(table.with_fuzzy(false).canonical("BP Connect"), None);
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("texaco", "texaco"), 0);
+    }
+
+    #[test]
+    fn test_table_files() -> Result<(), BrandTableError> {
+        let from_json = BrandTable::from_json(r#"{"BP": ["bp", "bp connect"]}"#)?;
+        let from_toml = BrandTable::from_toml(r#""BP" = ["bp", "bp connect"]"#)?;
+        assert_eq!(from_json, from_toml);
+        assert_eq!(from_json.format("BP Connect"), "BP");
+        assert_eq!(from_json.canonical("esso"), None);
+        Ok(())
+    }
+}
@@ -0,0 +1,240 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, FixedOffset};
+use serde::Serialize;
+
+use crate::error::TransformError;
+use crate::options::TransformOptions;
+use crate::process_feed_with;
+use crate::report::{FeedReport, ProcessedFeed};
+use crate::station_struts::StationPriceLastUpdated;
+
+/// Identifies which feed a station came from, and when that feed was updated
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StationSource {
+    /// Position of the feed in the input passed to [`process_feeds`]
+    pub feed: usize,
+    /// The station's most recent `lu` in that feed
+    pub lu: String,
+}
+
+/// A `site_id` that appeared in more than one feed
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SiteConflict {
+    pub site_id: String,
+    /// The most recently updated occurrence, which was kept
+    pub kept: StationSource,
+    /// The older occurrences, which were dropped
+    pub discarded: Vec<StationSource>,
+}
+
+/// The result of transforming many retailer feeds into one dataset
+#[derive(Debug, Default)]
+pub struct CombinedFeeds {
+    /// Deduplicated stations from every feed, ordered by `site_id`
+    pub stations: Vec<StationPriceLastUpdated>,
+    /// One entry per input feed, in input order
+    pub feeds: Vec<Result<FeedReport, TransformError>>,
+    /// Every `site_id` that was resolved between feeds
+    pub conflicts: Vec<SiteConflict>,
+}
+
+impl CombinedFeeds {
+    /// Returns `true` if any feed failed to transform
+    pub fn has_failed_feeds(&self) -> bool {
+        self.feeds.iter().any(Result::is_err)
+    }
+}
+
+/// Transforms many retailer feeds and combines them into one deduplicated dataset.
+///
+/// # Behavior
+///
+/// - Each feed is transformed independently, so every station keeps its own
+///   feed's `last_updated` timestamp
+/// - A feed that cannot be transformed is reported in `CombinedFeeds::feeds`
+///   and does not affect the others
+/// - When the same `site_id` appears in several feeds, the occurrence with the
+///   most recent `lu` is kept (the earliest feed wins a tie) and a
+///   [`SiteConflict`] is recorded
+///
+/// # Examples
+///
+/// ```rust
+/// use refuel_radar_transform::process_feeds;
+///
+/// let feeds = [
+///     r#"{"last_updated": "27/11/2024 11:45:32", "stations": []}"#,
+///     "not json",
+/// ];
+/// let combined = process_feeds(feeds);
+/// assert!(combined.feeds[0].is_ok());
+/// assert!(combined.has_failed_feeds());
+/// ```
+pub fn process_feeds<'a>(feeds: impl IntoIterator<Item = &'a str>) -> CombinedFeeds {
+    process_feeds_with(feeds, &TransformOptions::default())
+}
+
+/// Transforms and combines many retailer feeds using the given [`TransformOptions`].
+///
+/// See [`process_feeds`] for how feeds are combined. With the `parallel`
+/// feature, feeds are transformed concurrently; the result is identical to
+/// transforming them one after another.
+pub fn process_feeds_with<'a>(
+    feeds: impl IntoIterator<Item = &'a str>,
+    options: &TransformOptions,
+) -> CombinedFeeds {
+    #[cfg(feature = "parallel")]
+    let processed = {
+        use rayon::prelude::*;
+
+        let feeds: Vec<&str> = feeds.into_iter().collect();
+        feeds
+            .into_par_iter()
+            .map(|json_data| process_feed_with(json_data, options))
+            .collect()
+    };
+    #[cfg(not(feature = "parallel"))]
+    let processed = feeds
+        .into_iter()
+        .map(|json_data| process_feed_with(json_data, options))
+        .collect();
+    combine_feeds(processed)
+}
+
+/// Combines already transformed feeds, resolving duplicate `site_id`s.
+///
+/// Use this instead of [`process_feeds_with`] when feeds need different
+/// [`TransformOptions`], e.g. a per-feed timezone override:
+///
+/// ```rust
+/// use refuel_radar_transform::timestamp::TimezoneOptions;
+/// use refuel_radar_transform::{combine_feeds, process_feed, process_feed_with, TransformOptions};
+///
+/// let london = r#"{"last_updated": "27/11/2024 11:45:32", "stations": []}"#;
+/// let utc = r#"{"last_updated": "27/11/2024 11:45:32", "stations": []}"#;
+/// let utc_options = TransformOptions {
+///     timezone: TimezoneOptions::in_timezone(chrono_tz::UTC),
+///     ..TransformOptions::default()
+/// };
+/// let combined = combine_feeds(vec![process_feed(london), process_feed_with(utc, &utc_options)]);
+/// assert_eq!(combined.feeds.len(), 2);
+/// ```
+pub fn combine_feeds(processed: Vec<Result<ProcessedFeed, TransformError>>) -> CombinedFeeds {
+    let mut combined = CombinedFeeds::default();
+    let mut by_site: BTreeMap<String, (StationSource, StationPriceLastUpdated)> = BTreeMap::new();
+    let mut conflicts: BTreeMap<String, Vec<StationSource>> = BTreeMap::new();
+
+    for (feed, result) in processed.into_iter().enumerate() {
+        let feed_result = result.map(|processed| {
+            for station in processed.stations {
+                let source = StationSource {
+                    feed,
+                    lu: station.latest_lu().unwrap_or_default().to_string(),
+                };
+
+                match by_site.get_mut(&station.site_id) {
+                    Some((kept, existing)) => {
+                        let site_conflicts = conflicts.entry(station.site_id.clone()).or_default();
+                        if is_newer(&source.lu, &kept.lu) {
+                            site_conflicts.push(std::mem::replace(kept, source));
+                            *existing = station;
+                        } else {
+                            site_conflicts.push(source);
+                        }
+                    }
+                    None => {
+                        by_site.insert(station.site_id.clone(), (source, station));
+                    }
+                }
+            }
+            processed.report
+        });
+        combined.feeds.push(feed_result);
+    }
+
+    combined.conflicts = conflicts
+        .into_iter()
+        .map(|(site_id, discarded)| SiteConflict {
+            kept: by_site[&site_id].0.clone(),
+            site_id,
+            discarded,
+        })
+        .collect();
+    combined.stations = by_site.into_values().map(|(_, station)| station).collect();
+
+    combined
+}
+
+/// Compares two RFC 3339 timestamps, falling back to string order if either is unparseable
+fn is_newer(candidate: &str, current: &str) -> bool {
+    let parse = |lu: &str| DateTime::<FixedOffset>::parse_from_rfc3339(lu).ok();
+    match (parse(candidate), parse(current)) {
+        (Some(candidate), Some(current)) => candidate > current,
+        _ => candidate > current,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed(last_updated: &str, site_ids: &[&str]) -> String {
+        let stations: Vec<String> = site_ids
+            .iter()
+            .map(|site_id| {
+                format!(
+                    r#"{{
+                        "site_id": "{site_id}",
+                        "brand": "bp",
+                        "address": "1 High Street",
+                        "postcode": "AB1 2CD",
+                        "location": {{ "latitude": 51.5, "longitude": -0.1 }},
+                        "prices": {{ "E10": 139.9 }}
+                    }}"#
+                )
+            })
+            .collect();
+        format!(
+            r#"{{"last_updated": "{last_updated}", "stations": [{}]}}"#,
+            stations.join(",")
+        )
+    }
+
+    #[test]
+    fn test_most_recent_duplicate_wins() {
+        let older = feed("01/12/2024 09:00:00", &["b", "a"]);
+        let newer = feed("01/12/2024 10:00:00", &["a", "c"]);
+        let combined = process_feeds([older.as_str(), newer.as_str()]);
+
+        let site_ids: Vec<&str> = combined
+            .stations
+            .iter()
+            .map(|s| s.site_id.as_str())
+            .collect();
+        assert_eq!(site_ids, ["a", "b", "c"]);
+        assert_eq!(
+            combined.stations[0].prices[0].lu,
+            "2024-12-01T10:00:00+00:00"
+        );
+        assert_eq!(
+            combined.stations[1].prices[0].lu,
+            "2024-12-01T09:00:00+00:00"
+        );
+
+        assert_eq!(combined.conflicts.len(), 1);
+        assert_eq!(combined.conflicts[0].kept.feed, 1);
+        assert_eq!(combined.conflicts[0].discarded[0].feed, 0);
+    }
+
+    #[test]
+    fn test_failed_feed_does_not_affect_others() {
+        let good = feed("01/12/2024 09:00:00", &["a"]);
+        let combined = process_feeds(["{", good.as_str()]);
+        assert!(matches!(
+            combined.feeds[0],
+            Err(TransformError::InvalidJson { .. })
+        ));
+        assert_eq!(combined.stations.len(), 1);
+    }
+}
@@ -0,0 +1,260 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::bng::GridReference;
+use crate::fuel::FuelType;
+use crate::location::CoordinateForm;
+use crate::region::{Country, OnsRegion, Region};
+use crate::station_struts::{Location, PriceLastUpdated, StationPriceLastUpdated};
+
+/// Settings for the compact output profile
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompactOptions {
+    /// Decimal places kept for latitude and longitude (5 is roughly 1 metre)
+    pub coordinate_decimals: u32,
+}
+
+impl Default for CompactOptions {
+    fn default() -> Self {
+        CompactOptions {
+            coordinate_decimals: 5,
+        }
+    }
+}
+
+/// A station in the compact output profile
+///
+/// # Keys
+///
+/// - `id`: site ID
+/// - `b`: brand, `rb`: raw brand (omitted when identical to the brand)
+/// - `a`: address, `pc`: postcode
+/// - `l`: location, with `la` latitude and `lo` longitude, and `e` easting and
+///   `n` northing when the station has a grid reference
+/// - `r`: region, when the station has one (see [`CompactRegion`])
+/// - `p`: price history (omitted when empty), each entry holding fuel codes
+///   and `lu`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CompactStation {
+    pub id: String,
+    pub b: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rb: Option<String>,
+    pub a: String,
+    pub pc: String,
+    pub l: CompactLocation,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r: Option<CompactRegion>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub p: Vec<PriceLastUpdated>,
+}
+
+/// A location in the compact output profile
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CompactLocation {
+    pub la: f64,
+    pub lo: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub e: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub n: Option<f64>,
+}
+
+/// A region in the compact output profile: `a` postcode area, `an` area name,
+/// `o` ONS region and `c` country
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CompactRegion {
+    pub a: String,
+    pub an: String,
+    pub o: OnsRegion,
+    pub c: Country,
+}
+
+/// Converts stations to the compact output profile.
+///
+/// # Compaction
+///
+/// - Short keys for every field (see [`CompactStation`])
+/// - Coordinates rounded to `options.coordinate_decimals` places
+/// - Prices rounded to one decimal place
+/// - Price history entries with no prices are omitted
+///
+/// # Examples
+///
+/// ```rust
+/// use refuel_radar_transform::compact::{from_compact_str, to_compact, CompactOptions};
+/// use refuel_radar_transform::process_data;
+///
+/// let json = r#"{"last_updated": "27/11/2024 11:45:32", "stations": []}"#;
+/// let compact = to_compact(&process_data(json), &CompactOptions::default());
+/// let output = serde_json::to_string(&compact).unwrap();
+/// assert!(from_compact_str(&output).unwrap().is_empty());
+/// ```
+pub fn to_compact(
+    stations: &[StationPriceLastUpdated],
+    options: &CompactOptions,
+) -> Vec<CompactStation> {
+    let coordinate_scale = 10f64.powi(options.coordinate_decimals as i32);
+    let round_coordinate = |value: f64| (value * coordinate_scale).round() / coordinate_scale;
+
+    stations
+        .iter()
+        .map(|station| CompactStation {
+            id: station.site_id.clone(),
+            b: station.brand.clone(),
+            rb: (station.raw_brand != station.brand).then(|| station.raw_brand.clone()),
+            a: station.address.clone(),
+            pc: station.postcode.clone(),
+            l: CompactLocation {
+                la: round_coordinate(station.location.latitude),
+                lo: round_coordinate(station.location.longitude),
+                e: station.location.grid.map(|grid| grid.easting),
+                n: station.location.grid.map(|grid| grid.northing),
+            },
+            r: station.region.as_ref().map(|region| CompactRegion {
+                a: region.area.clone(),
+                an: region.area_name.clone(),
+                o: region.ons_region,
+                c: region.country,
+            }),
+            p: station
+                .prices
+                .iter()
+                .filter(|snapshot| !snapshot.prices.is_empty())
+                .map(|snapshot| PriceLastUpdated {
+                    prices: round_prices(&snapshot.prices),
+                    lu: snapshot.lu.clone(),
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+/// Reads stations written in the compact output profile.
+///
+/// A missing raw brand is restored as the brand, and a missing price history
+/// as an empty one. Grid references and regions are restored when present.
+pub fn from_compact(compact: Vec<CompactStation>) -> Vec<StationPriceLastUpdated> {
+    compact
+        .into_iter()
+        .map(StationPriceLastUpdated::from)
+        .collect()
+}
+
+/// Deserializes a JSON array of compact stations; see [`from_compact`]
+pub fn from_compact_str(json: &str) -> serde_json::Result<Vec<StationPriceLastUpdated>> {
+    serde_json::from_str(json).map(from_compact)
+}
+
+impl From<CompactStation> for StationPriceLastUpdated {
+    fn from(compact: CompactStation) -> Self {
+        StationPriceLastUpdated {
+            raw_brand: compact.rb.unwrap_or_else(|| compact.b.clone()),
+            site_id: compact.id,
+            brand: compact.b,
+            address: compact.a,
+            postcode: compact.pc,
+            location: Location {
+                latitude: compact.l.la,
+                longitude: compact.l.lo,
+                grid: match (compact.l.e, compact.l.n) {
+                    (Some(easting), Some(northing)) => Some(GridReference { easting, northing }),
+                    _ => None,
+                },
+                form: CoordinateForm::Number,
+            },
+            prices: compact.p,
+            region: compact.r.map(|region| Region {
+                area: region.a,
+                area_name: region.an,
+                ons_region: region.o,
+                country: region.c,
+            }),
+        }
+    }
+}
+
+fn round_prices(prices: &HashMap<FuelType, f64>) -> HashMap<FuelType, f64> {
+    prices
+        .iter()
+        .map(|(fuel, &price)| (fuel.clone(), (price * 10.0).round() / 10.0))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{process_feed_with, try_process_data, TransformOptions};
+
+    #[test]
+    fn test_compact_round_trip() {
+        let json = r#"{
+            "last_updated": "27/11/2024 11:45:32",
+            "stations": [{
+                "site_id": "a",
+                "brand": "bp",
+                "address": "1 High Street",
+                "postcode": "AB1 2CD",
+                "location": { "latitude": 51.512345678, "longitude": -0.123456789 },
+                "prices": { "E10": 131.94 }
+            }, {
+                "site_id": "b",
+                "brand": "Esso",
+                "address": "2 High Street",
+                "postcode": "AB1 2CE",
+                "location": { "latitude": 51.5, "longitude": -0.1 },
+                "prices": {}
+            }]
+        }"#;
+        let stations = try_process_data(json).unwrap();
+        let options = CompactOptions {
+            coordinate_decimals: 3,
+        };
+        let output = serde_json::to_string(&to_compact(&stations, &options)).unwrap();
+        assert_eq!(
+            output,
+            r#"[{"id":"a","b":"BP","rb":"bp","a":"1 High Street","pc":"AB1 2CD","l":{"la":51.512,"lo":-0.123},"p":[{"E10":131.9,"lu":"2024-11-27T11:45:32+00:00"}]},"#
+                .to_string()
+                + r#"{"id":"b","b":"Esso","a":"2 High Street","pc":"AB1 2CE","l":{"la":51.5,"lo":-0.1}}]"#
+        );
+
+        let read_back = from_compact_str(&output).unwrap();
+        assert_eq!(read_back[0].raw_brand, "bp");
+        assert_eq!(read_back[0].location.latitude, 51.512);
+        assert_eq!(read_back[0].prices[0].prices[&FuelType::E10], 131.9);
+        assert_eq!(read_back[1].raw_brand, "Esso");
+        assert!(read_back[1].prices.is_empty());
+    }
+
+    #[test]
+    fn test_grid_references_and_regions() {
+        let json = r#"{
+            "last_updated": "27/11/2024 11:45:32",
+            "stations": [{
+                "site_id": "a",
+                "brand": "bp",
+                "address": "1 High Street",
+                "postcode": "SW1A 1AA",
+                "location": { "easting": 529090, "northing": 179645 },
+                "prices": { "E10": 131.9 }
+            }]
+        }"#;
+        let options = TransformOptions {
+            grid_references: true,
+            regions: true,
+            ..TransformOptions::default()
+        };
+        let stations = process_feed_with(json, &options).unwrap().stations;
+        let output =
+            serde_json::to_value(to_compact(&stations, &CompactOptions::default())).unwrap();
+        assert_eq!(output[0]["l"]["e"], 529090.0);
+        assert_eq!(output[0]["l"]["n"], 179645.0);
+        assert_eq!(output[0]["r"]["an"], "London SW");
+        assert_eq!(output[0]["r"]["o"], "London");
+
+        let read_back = from_compact_str(&output.to_string()).unwrap();
+        assert_eq!(read_back[0].location.grid, stations[0].location.grid);
+        assert_eq!(read_back[0].region, stations[0].region);
+    }
+}
@@ -0,0 +1,173 @@
+use std::collections::{BTreeSet, HashMap};
+use std::io::{self, Write};
+
+use crate::fuel::FuelType;
+use crate::station_struts::StationPriceLastUpdated;
+
+/// The fixed columns written before the fuel price columns
+const FIXED_COLUMNS: [&str; 7] = [
+    "site_id",
+    "brand",
+    "address",
+    "postcode",
+    "latitude",
+    "longitude",
+    "lu",
+];
+
+/// Which price snapshots [`write_csv`] writes rows for
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CsvMode {
+    /// One row per station per price snapshot
+    #[default]
+    History,
+    /// One row per station, with the latest price for each fuel type
+    LatestOnly,
+}
+
+/// Writes stations and prices as CSV, with a header row.
+///
+/// # Columns
+///
+/// - `site_id`, `brand`, `address`, `postcode`, `latitude`, `longitude`, `lu`
+/// - One column per fuel type: E5, E10, B7 and SDV always, followed by any
+///   other fuel types present in `stations` in alphabetical order
+///
+/// A cell is left empty when a row has no price for that fuel type. Fields
+/// containing commas, quotes or line breaks are quoted as per RFC 4180.
+///
+/// # Examples
+///
+/// ```rust
+/// use refuel_radar_transform::csv::{write_csv, CsvMode};
+///
+/// let mut output = Vec::new();
+/// write_csv(&mut output, &[], CsvMode::History).unwrap();
+/// assert_eq!(
+///     String::from_utf8(output).unwrap(),
+///     "site_id,brand,address,postcode,latitude,longitude,lu,E5,E10,B7,SDV\n"
+/// );
+/// ```
+pub fn write_csv<W: Write>(
+    mut writer: W,
+    stations: &[StationPriceLastUpdated],
+    mode: CsvMode,
+) -> io::Result<()> {
+    let fuel_columns = fuel_columns(stations);
+
+    let header: Vec<&str> = FIXED_COLUMNS
+        .iter()
+        .copied()
+        .chain(fuel_columns.iter().map(FuelType::code))
+        .collect();
+    write_record(&mut writer, header)?;
+
+    for station in stations {
+        let latitude = station.location.latitude.to_string();
+        let longitude = station.location.longitude.to_string();
+
+        let mut write_row = |lu: &str, prices: &HashMap<FuelType, f64>| {
+            let prices: Vec<String> = fuel_columns
+                .iter()
+                .map(|fuel| prices.get(fuel).map_or_else(String::new, f64::to_string))
+                .collect();
+            let record = [
+                station.site_id.as_str(),
+                &station.brand,
+                &station.address,
+                &station.postcode,
+                &latitude,
+                &longitude,
+                lu,
+            ]
+            .into_iter()
+            .chain(prices.iter().map(String::as_str));
+            write_record(&mut writer, record)
+        };
+
+        match mode {
+            CsvMode::History => {
+                for snapshot in &station.prices {
+                    write_row(&snapshot.lu, &snapshot.prices)?;
+                }
+            }
+            CsvMode::LatestOnly => {
+                write_row(station.latest_lu().unwrap_or(""), &station.latest_prices())?;
+            }
+        }
+    }
+
+    writer.flush()
+}
+
+/// The known fuel types followed by every other fuel type used, alphabetically
+fn fuel_columns(stations: &[StationPriceLastUpdated]) -> Vec<FuelType> {
+    let others: BTreeSet<&FuelType> = stations
+        .iter()
+        .flat_map(|station| &station.prices)
+        .flat_map(|snapshot| snapshot.prices.keys())
+        .filter(|fuel| fuel.is_other())
+        .collect();
+
+    FuelType::KNOWN
+        .into_iter()
+        .chain(others.into_iter().cloned())
+        .collect()
+}
+
+fn write_record<'a, W: Write>(
+    writer: &mut W,
+    fields: impl IntoIterator<Item = &'a str>,
+) -> io::Result<()> {
+    for (i, field) in fields.into_iter().enumerate() {
+        if i > 0 {
+            writer.write_all(b",")?;
+        }
+        if field.contains([',', '"', '\n', '\r']) {
+            write!(writer, "\"{}\"", field.replace('"', "\"\""))?;
+        } else {
+            writer.write_all(field.as_bytes())?;
+        }
+    }
+    writer.write_all(b"\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::try_process_data;
+
+    #[test]
+    fn test_rows_per_snapshot_and_latest() {
+        let json = r#"{
+            "last_updated": "27/11/2024 12:00:00",
+            "stations": [{
+                "site_id": "a",
+                "brand": "bp",
+                "address": "1 High Street, \"Top\" End",
+                "postcode": "AB1 2CD",
+                "location": { "latitude": 51.5, "longitude": -0.1 },
+                "prices": {
+                    "E10": { "price": 131.9, "last_updated": "27/11/2024 08:00:00" },
+                    "LPG": 89.9
+                }
+            }]
+        }"#;
+        let stations = try_process_data(json).unwrap();
+
+        let mut output = Vec::new();
+        write_csv(&mut output, &stations, CsvMode::History).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "site_id,brand,address,postcode,latitude,longitude,lu,E5,E10,B7,SDV,LPG\n\
+             a,BP,\"1 High Street, \"\"Top\"\" End\",AB1 2CD,51.5,-0.1,2024-11-27T08:00:00+00:00,,131.9,,,\n\
+             a,BP,\"1 High Street, \"\"Top\"\" End\",AB1 2CD,51.5,-0.1,2024-11-27T12:00:00+00:00,,,,,89.9\n"
+        );
+
+        let mut output = Vec::new();
+        write_csv(&mut output, &stations, CsvMode::LatestOnly).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(output.lines().count(), 2);
+        assert!(output.ends_with(",2024-11-27T12:00:00+00:00,,131.9,,,89.9\n"));
+    }
+}
@@ -0,0 +1,126 @@
+use thiserror::Error;
+
+use crate::fuel::FuelType;
+use crate::timestamp::TimestampError;
+
+/// Errors that can occur while transforming a retailer feed
+///
+/// Each variant carries enough context to identify which part of which feed
+/// was at fault when logged.
+#[derive(Debug, Error)]
+pub enum TransformError {
+    /// The input is not valid JSON
+    #[error("invalid JSON at line {line}, column {column} (byte offset {offset}): {source}")]
+    InvalidJson {
+        line: usize,
+        column: usize,
+        offset: usize,
+        #[source]
+        source: serde_json::Error,
+    },
+
+    /// The feed has no top-level `last_updated` field
+    #[error("missing `last_updated` field")]
+    MissingLastUpdated,
+
+    /// The `last_updated` field could not be parsed as a timestamp
+    #[error("unparseable `last_updated` timestamp {value:?}: {source}")]
+    InvalidTimestamp {
+        value: String,
+        #[source]
+        source: TimestampError,
+    },
+
+    /// A field does not have the expected JSON type (e.g. `stations` is not an array)
+    #[error("unexpected shape at `{path}`: expected {expected}, found {found}")]
+    UnexpectedShape {
+        path: String,
+        expected: &'static str,
+        found: &'static str,
+    },
+
+    /// The feed could not be read from its source
+    #[error("cannot read feed: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+impl TransformError {
+    /// Builds an `InvalidJson` error, resolving serde's line/column into a byte offset of `input`
+    pub(crate) fn invalid_json(input: &str, source: serde_json::Error) -> Self {
+        let line = source.line();
+        let column = source.column();
+        let offset = input
+            .split_inclusive('\n')
+            .take(line.saturating_sub(1))
+            .map(str::len)
+            .sum::<usize>()
+            + column.saturating_sub(1);
+
+        TransformError::InvalidJson {
+            line,
+            column,
+            offset: offset.min(input.len()),
+            source,
+        }
+    }
+}
+
+/// Errors that can occur while loading a brand alias table
+#[derive(Debug, Error)]
+pub enum BrandTableError {
+    /// The table file could not be read
+    #[error("cannot read brand table: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// The table is not a valid JSON brand table
+    #[error("invalid JSON brand table: {0}")]
+    Json(#[from] serde_json::Error),
+
+    /// The table is not a valid TOML brand table
+    #[error("invalid TOML brand table: {0}")]
+    Toml(#[from] toml::de::Error),
+}
+
+/// Errors from building a station or location through its validating constructor
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum ValidationError {
+    /// A latitude is not finite or lies outside -90 to 90 degrees
+    #[error("invalid latitude {0}: must be finite and between -90 and 90")]
+    InvalidLatitude(f64),
+
+    /// A longitude is not finite or lies outside -180 to 180 degrees
+    #[error("invalid longitude {0}: must be finite and between -180 and 180")]
+    InvalidLongitude(f64),
+
+    /// An easting or northing is not finite or lies outside the British
+    /// National Grid
+    #[error("grid reference {easting}, {northing} is not on the British National Grid")]
+    OffGrid { easting: f64, northing: f64 },
+
+    /// A postcode does not follow the UK postcode format
+    #[error("invalid postcode {0:?}")]
+    InvalidPostcode(String),
+
+    /// A station has an empty (or whitespace-only) site ID
+    #[error("site ID is empty")]
+    EmptySiteId,
+
+    /// A price is not finite or not positive
+    #[error("invalid {fuel_type} price {price}: must be finite and positive")]
+    InvalidPrice { fuel_type: FuelType, price: f64 },
+}
+
+/// Describes the JSON type of an unparsed value, for `UnexpectedShape` errors
+///
+/// Only the first byte of `json` is looked at, as it decides the type of any
+/// valid JSON value.
+pub(crate) fn json_type_name(json: &[u8]) -> &'static str {
+    match json.first() {
+        Some(b'n') => "null",
+        Some(b't' | b'f') => "boolean",
+        Some(b'"') => "string",
+        Some(b'[') => "array",
+        Some(b'{') => "object",
+        _ => "number",
+    }
+}
@@ -0,0 +1,115 @@
+use std::convert::Infallible;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// A canonical fuel grade, as published under the UK open fuel price scheme
+///
+/// # Parsing
+///
+/// Price keys are matched case-insensitively, ignoring surrounding whitespace
+/// and treating `_`/`-` as spaces, against the codes and common aliases:
+///
+/// - `E5`: "e5", "super unleaded", "premium unleaded"
+/// - `E10`: "e10", "unleaded", "petrol"
+/// - `B7`: "b7", "diesel", "standard diesel"
+/// - `SDV`: "sdv", "super diesel", "premium diesel"
+///
+/// Anything else becomes `Other`, holding the trimmed key.
+///
+/// # Serialization
+///
+/// Serializes to the canonical code ("E5", "E10", "B7", "SDV") or the `Other` key.
+///
+/// # Examples
+///
+/// ```rust
+/// use refuel_radar_transform::fuel::FuelType;
+///
+/// assert_eq!("Unleaded ".parse::<FuelType>().unwrap(), FuelType::E10);
+/// assert_eq!(FuelType::Sdv.to_string(), "SDV");
+/// assert_eq!("LPG".parse::<FuelType>().unwrap(), FuelType::Other("LPG".to_string()));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum FuelType {
+    /// Super unleaded petrol
+    E5,
+    /// Standard unleaded petrol
+    E10,
+    /// Standard diesel
+    B7,
+    /// Super (premium) diesel
+    Sdv,
+    /// Any key that is not a recognised fuel grade
+    Other(String),
+}
+
+impl FuelType {
+    /// The recognised fuel grades, in output order
+    pub const KNOWN: [FuelType; 4] = [FuelType::E5, FuelType::E10, FuelType::B7, FuelType::Sdv];
+
+    /// The canonical code for this fuel type
+    pub fn code(&self) -> &str {
+        match self {
+            FuelType::E5 => "E5",
+            FuelType::E10 => "E10",
+            FuelType::B7 => "B7",
+            FuelType::Sdv => "SDV",
+            FuelType::Other(key) => key,
+        }
+    }
+
+    /// Returns `true` for the `Other` escape hatch
+    pub fn is_other(&self) -> bool {
+        matches!(self, FuelType::Other(_))
+    }
+}
+
+impl FromStr for FuelType {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let key = s
+            .trim()
+            .to_lowercase()
+            .replace(['_', '-'], " ")
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        Ok(match key.as_str() {
+            "e5" | "super unleaded" | "premium unleaded" => FuelType::E5,
+            "e10" | "unleaded" | "petrol" => FuelType::E10,
+            "b7" | "diesel" | "standard diesel" => FuelType::B7,
+            "sdv" | "super diesel" | "premium diesel" => FuelType::Sdv,
+            _ => FuelType::Other(s.trim().to_string()),
+        })
+    }
+}
+
+impl fmt::Display for FuelType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+impl Serialize for FuelType {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.code())
+    }
+}
+
+impl<'de> Deserialize<'de> for FuelType {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let key = String::deserialize(deserializer)?;
+        let Ok(fuel_type) = key.parse();
+        Ok(fuel_type)
+    }
+}
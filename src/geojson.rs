@@ -0,0 +1,156 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::bng::GridReference;
+use crate::fuel::FuelType;
+use crate::region::Region;
+use crate::station_struts::{serialize_prices, PriceLastUpdated, StationPriceLastUpdated};
+
+/// Settings for [`to_geojson`]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GeoJsonOptions {
+    /// Adds each station's full price history as a `history` property
+    pub include_history: bool,
+}
+
+/// A GeoJSON `FeatureCollection` of stations
+#[derive(Debug, Serialize)]
+pub struct FeatureCollection<'a> {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    features: Vec<Feature<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+struct Feature<'a> {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    id: &'a str,
+    geometry: Point,
+    properties: Properties<'a>,
+}
+
+#[derive(Debug, Serialize)]
+struct Point {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    /// `[longitude, latitude]`, as GeoJSON requires
+    coordinates: [f64; 2],
+}
+
+#[derive(Debug, Serialize)]
+struct Properties<'a> {
+    site_id: &'a str,
+    brand: &'a str,
+    address: &'a str,
+    postcode: &'a str,
+    #[serde(flatten)]
+    grid: Option<GridReference>,
+    lu: Option<&'a str>,
+    #[serde(serialize_with = "serialize_prices")]
+    prices: HashMap<FuelType, f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    region: Option<&'a Region>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    history: Option<&'a [PriceLastUpdated]>,
+}
+
+/// Builds a GeoJSON `FeatureCollection` with one Point feature per station.
+///
+/// # Feature Properties
+///
+/// - `site_id`, `brand`, `address` and `postcode`
+/// - `easting` and `northing`: the British National Grid reference, only for
+///   stations transformed with [`crate::TransformOptions::grid_references`]
+/// - `prices`: the latest price for each fuel type
+/// - `lu`: the most recent update time in the station's price history
+/// - `region`: the postcode area, ONS region and country, only for stations
+///   transformed with [`crate::TransformOptions::regions`]
+/// - `history`: the full price history, only with `include_history`
+///
+/// # Examples
+///
+/// ```rust
+/// use refuel_radar_transform::geojson::{to_geojson, GeoJsonOptions};
+/// use refuel_radar_transform::process_data;
+///
+/// let json = r#"{"last_updated": "27/11/2024 11:45:32", "stations": []}"#;
+/// let stations = process_data(json);
+/// let geojson = serde_json::to_string(&to_geojson(&stations, &GeoJsonOptions::default())).unwrap();
+/// assert_eq!(geojson, r#"{"type":"FeatureCollection","features":[]}"#);
+/// ```
+pub fn to_geojson<'a>(
+    stations: &'a [StationPriceLastUpdated],
+    options: &GeoJsonOptions,
+) -> FeatureCollection<'a> {
+    let features = stations
+        .iter()
+        .map(|station| Feature {
+            kind: "Feature",
+            id: &station.site_id,
+            geometry: Point {
+                kind: "Point",
+                coordinates: [station.location.longitude, station.location.latitude],
+            },
+            properties: Properties {
+                site_id: &station.site_id,
+                brand: &station.brand,
+                address: &station.address,
+                postcode: &station.postcode,
+                grid: station.location.grid,
+                lu: station.latest_lu(),
+                prices: station.latest_prices(),
+                region: station.region.as_ref(),
+                history: options.include_history.then_some(station.prices.as_slice()),
+            },
+        })
+        .collect();
+
+    FeatureCollection {
+        kind: "FeatureCollection",
+        features,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::try_process_data;
+
+    #[test]
+    fn test_feature_collection() {
+        let json = r#"{
+            "last_updated": "27/11/2024 11:45:32",
+            "stations": [{
+                "site_id": "a",
+                "brand": "bp",
+                "address": "1 High Street",
+                "postcode": "AB1 2CD",
+                "location": { "latitude": 51.5, "longitude": -0.1 },
+                "prices": { "E10": 131.9 }
+            }]
+        }"#;
+        let stations = try_process_data(json).unwrap();
+
+        let geojson =
+            serde_json::to_value(to_geojson(&stations, &GeoJsonOptions::default())).unwrap();
+        let feature = &geojson["features"][0];
+        assert_eq!(
+            feature["geometry"]["coordinates"],
+            serde_json::json!([-0.1, 51.5])
+        );
+        assert_eq!(feature["properties"]["prices"]["E10"], 131.9);
+        assert_eq!(feature["properties"]["lu"], "2024-11-27T11:45:32+00:00");
+        assert!(feature["properties"].get("history").is_none());
+
+        let options = GeoJsonOptions {
+            include_history: true,
+        };
+        let geojson = serde_json::to_value(to_geojson(&stations, &options)).unwrap();
+        assert_eq!(
+            geojson["features"][0]["properties"]["history"][0]["E10"],
+            131.9
+        );
+    }
+}
@@ -0,0 +1,226 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, FixedOffset};
+
+use crate::error::TransformError;
+use crate::process_feed;
+use crate::station_struts::{PriceLastUpdated, StationPriceLastUpdated};
+
+/// Merges newly transformed stations into previously produced price history.
+///
+/// # Merge Rules
+///
+/// - Stations are matched by `site_id`
+/// - Each fuel's new price is compared with its latest price across the
+///   station's whole history; only the fuels whose price changed are added,
+///   as a new `PriceLastUpdated` entry, and an entry already recorded (prices
+///   and `lu`) is not added again
+/// - Entries are kept in `lu` order, oldest first
+/// - Station metadata (brand, raw brand, address, postcode, location and
+///   region) is replaced with the values from `latest`
+/// - Stations only present in `latest` are appended in feed order
+/// - Stations only present in `history` are kept unchanged
+///
+/// # Parameters
+///
+/// - `history`: Previously produced stations, each with its price history
+/// - `latest`: Stations from a newer feed
+///
+/// # Returns
+///
+/// The merged stations, in `history` order followed by any new stations
+pub fn merge_history(
+    history: Vec<StationPriceLastUpdated>,
+    latest: Vec<StationPriceLastUpdated>,
+) -> Vec<StationPriceLastUpdated> {
+    let mut merged = history;
+    let mut positions: HashMap<String, usize> = merged
+        .iter()
+        .enumerate()
+        .map(|(i, station)| (station.site_id.clone(), i))
+        .collect();
+
+    for station in latest {
+        match positions.get(&station.site_id) {
+            Some(&i) => {
+                let existing = &mut merged[i];
+                existing.brand = station.brand;
+                existing.raw_brand = station.raw_brand;
+                existing.address = station.address;
+                existing.postcode = station.postcode;
+                existing.location = station.location;
+                existing.region = station.region;
+
+                for snapshot in station.prices {
+                    append_changed_prices(existing, snapshot);
+                }
+            }
+            None => {
+                positions.insert(station.site_id.clone(), merged.len());
+                merged.push(station);
+            }
+        }
+    }
+
+    merged
+}
+
+/// Adds the prices in `snapshot` that differ from the station's latest prices
+/// to its history, keeping entries in `lu` order.
+///
+/// A feed may split a station's prices across several entries, one per update
+/// time, so each fuel is compared with its latest price across the whole
+/// history rather than with the last entry alone.
+fn append_changed_prices(station: &mut StationPriceLastUpdated, snapshot: PriceLastUpdated) {
+    let changed = if station.prices.is_empty() {
+        snapshot
+    } else {
+        let latest = station.latest_prices();
+        PriceLastUpdated {
+            prices: snapshot
+                .prices
+                .into_iter()
+                .filter(|(fuel_type, price)| latest.get(fuel_type) != Some(price))
+                .collect(),
+            lu: snapshot.lu,
+        }
+    };
+    if (changed.prices.is_empty() && !station.prices.is_empty())
+        || station.prices.contains(&changed)
+    {
+        return;
+    }
+
+    let lu = lu_order(&changed.lu);
+    let position = station
+        .prices
+        .partition_point(|entry| lu_order(&entry.lu) <= lu);
+    station.prices.insert(position, changed);
+}
+
+/// Sort key for an `lu`, comparing instants where both parse as RFC 3339
+fn lu_order(lu: &str) -> (Option<DateTime<FixedOffset>>, &str) {
+    (DateTime::parse_from_rfc3339(lu).ok(), lu)
+}
+
+/// Transforms a feed and merges it into previously produced price history.
+///
+/// Convenience wrapper around [`process_feed`] and [`merge_history`]; stations
+/// rejected from the feed leave their existing history untouched.
+///
+/// # Errors
+///
+/// Returns a [`TransformError`] if the feed itself cannot be transformed
+pub fn merge_feed(
+    history: Vec<StationPriceLastUpdated>,
+    json_data: &str,
+) -> Result<Vec<StationPriceLastUpdated>, TransformError> {
+    let feed = process_feed(json_data)?;
+    Ok(merge_history(history, feed.stations))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fuel::FuelType;
+    use crate::region::OnsRegion;
+    use crate::{process_feed_with, try_process_data, TransformOptions};
+
+    fn feed(last_updated: &str, brand: &str, e10: f64) -> String {
+        format!(
+            r#"{{
+                "last_updated": "{last_updated}",
+                "stations": [{{
+                    "site_id": "a",
+                    "brand": "{brand}",
+                    "address": "1 High Street",
+                    "postcode": "AB1 2CD",
+                    "location": {{ "latitude": 51.5, "longitude": -0.1 }},
+                    "prices": {{ "E10": {e10} }}
+                }}]
+            }}"#
+        )
+    }
+
+    #[test]
+    fn test_unchanged_prices_are_not_appended() -> Result<(), TransformError> {
+        let history = try_process_data(&feed("01/12/2024 09:00:00", "bp", 139.9))?;
+        let merged = merge_feed(history, &feed("01/12/2024 10:00:00", "bp", 139.9))?;
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].prices.len(), 1);
+        assert_eq!(merged[0].prices[0].lu, "2024-12-01T09:00:00+00:00");
+        Ok(())
+    }
+
+    #[test]
+    fn test_changed_prices_are_appended() -> Result<(), TransformError> {
+        let history = try_process_data(&feed("01/12/2024 09:00:00", "bp", 139.9))?;
+        let merged = merge_feed(history, &feed("01/12/2024 10:00:00", "esso", 138.9))?;
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].brand, "Esso");
+        assert_eq!(merged[0].prices.len(), 2);
+        assert_eq!(merged[0].prices[1].prices[&FuelType::E10], 138.9);
+        Ok(())
+    }
+
+    #[test]
+    fn test_region_is_replaced() -> Result<(), TransformError> {
+        let history = try_process_data(&feed("01/12/2024 09:00:00", "bp", 139.9))?;
+        assert_eq!(history[0].region, None);
+
+        let options = TransformOptions {
+            regions: true,
+            ..TransformOptions::default()
+        };
+        let latest = feed("01/12/2024 10:00:00", "bp", 139.9).replace("AB1 2CD", "AB10 1AA");
+        let latest = process_feed_with(&latest, &options)?.stations;
+        let merged = merge_history(history, latest);
+
+        let region = merged[0].region.as_ref().unwrap();
+        assert_eq!(region.ons_region, OnsRegion::Scotland);
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_snapshots_are_compared_per_fuel() -> Result<(), TransformError> {
+        let prices = r#""prices": { "E10": 139.9 }"#;
+        let split = r#""prices": {
+            "E10": { "price": 131.9, "lu": "27/11/2024 08:00:00" },
+            "B7": { "price": 139.9, "lu": "27/11/2024 10:00:00" }
+        }"#;
+        let history =
+            try_process_data(&feed("27/11/2024 12:00:00", "bp", 139.9).replace(prices, split))?;
+        assert_eq!(history[0].prices.len(), 2);
+
+        // Unchanged prices under a new station-wide time add nothing
+        let unchanged = r#""prices": { "E10": 131.9, "B7": 139.9 }"#;
+        let latest =
+            try_process_data(&feed("27/11/2024 12:00:00", "bp", 139.9).replace(prices, unchanged))?;
+        let merged = merge_history(history.clone(), latest);
+        assert_eq!(merged[0].prices, history[0].prices);
+
+        // Only the fuel that changed is added, in time order
+        let moved = r#""prices": {
+            "E10": { "price": 131.9, "lu": "27/11/2024 11:00:00" },
+            "B7": { "price": 138.9, "lu": "27/11/2024 09:00:00" }
+        }"#;
+        let latest =
+            try_process_data(&feed("27/11/2024 12:00:00", "bp", 139.9).replace(prices, moved))?;
+        let merged = merge_history(history, latest);
+        let entries: Vec<_> = merged[0]
+            .prices
+            .iter()
+            .map(|entry| (entry.lu.as_str(), entry.prices.len()))
+            .collect();
+        assert_eq!(
+            entries,
+            [
+                ("2024-11-27T08:00:00+00:00", 1),
+                ("2024-11-27T09:00:00+00:00", 1),
+                ("2024-11-27T10:00:00+00:00", 1),
+            ]
+        );
+        assert_eq!(merged[0].prices[1].prices[&FuelType::B7], 138.9);
+        Ok(())
+    }
+}
@@ -1,13 +1,52 @@
+use bng::GridReference;
+use brand::{BrandMatch, BrandMatchKind};
+use std::collections::{BTreeMap, HashMap};
+
 use chrono::{DateTime, NaiveDateTime, ParseError, Utc};
-use station_struts::{FuelStationData, PriceLastUpdated, StationPriceLastUpdated, StationPrices};
+use error::json_type_name;
+use location::{check_coordinate_form, check_location};
+use postcode::normalise_postcode;
+use region::classify;
+use serde::Deserialize;
+use serde_json::value::RawValue;
+use station_struts::{
+    FuelStationData, PriceLastUpdated, PricesHashMap, StationPriceLastUpdated, StationPrices,
+};
+use timestamp::{local_to_utc, parse_timestamp, TimestampError, TimezoneOptions};
+use units::normalise_price_units;
 
+pub mod bng;
+pub mod brand;
+pub mod combine;
+pub mod compact;
+pub mod csv;
+pub mod error;
+pub mod fuel;
+pub mod geojson;
+pub mod history;
+pub mod location;
+pub mod options;
+#[cfg(feature = "parallel")]
+mod parallel;
+pub mod postcode;
+pub mod region;
+pub mod report;
 pub mod station_struts;
+pub mod stream;
+pub mod timestamp;
+pub mod units;
+
+pub use combine::{combine_feeds, process_feeds, process_feeds_with, CombinedFeeds, SiteConflict};
+pub use error::{TransformError, ValidationError};
+pub use fuel::FuelType;
+pub use history::{merge_feed, merge_history};
+pub use options::TransformOptions;
+pub use report::{FeedReport, ProcessedFeed, StationRejection, TimestampIssue};
+
 /// Processes fuel station data from a JSON string, transforming it into a structured format.
 ///
-/// This function performs a multi-step transformation of fuel station data:
-/// 1. Deserializes the input JSON into a `FuelStationData` struct
-/// 2. Extracts the last updated timestamp and stations
-/// 3. Processes station data and adds last updated information
+/// This is the panicking counterpart of [`try_process_data`]; prefer that function
+/// when a malformed feed must not take down the caller.
 ///
 /// # Parameters
 ///
@@ -20,75 +59,224 @@ pub mod station_struts;
 /// - Prices
 /// - Last updated timestamp
 ///
-/// # Process Flow
+/// # Examples
+///
+/// ```rust
+/// use refuel_radar_transform::process_data;
+///
+/// let json = r#"{"last_updated": "27/11/2024 11:45:32", "stations": []}"#;
+/// let processed_stations = process_data(json);
+/// assert!(processed_stations.is_empty());
+/// ```
+///
+/// # Potential Panics
+///
+/// - Panics with the [`TransformError`] message if the feed cannot be transformed
+pub fn process_data(json_data: &str) -> Vec<StationPriceLastUpdated> {
+    try_process_data(json_data).unwrap_or_else(|e| panic!("Invalid feed: {e}"))
+}
+
+/// Fallible version of [`process_data`].
+///
+/// This function performs a multi-step transformation of fuel station data:
+/// 1. Deserializes the input JSON and checks the shape of `last_updated` and `stations`
+/// 2. Parses the last updated timestamp
+/// 3. Processes station data and adds last updated information
+///
+/// # Parameters
 ///
-/// - Deserialize JSON using `serde_json`
-/// - Parse the last updated timestamp
-/// - Process individual stations
-/// - Add last updated timestamp to each station's price information
+/// - `json_data`: A JSON-formatted string containing fuel station information
 ///
 /// # Behavior
 ///
-/// - Returns an empty vector if no stations are present
-/// - Propagates parsing errors via `expect()`
+/// - Returns an empty vector if no stations are present (the timestamp is not parsed)
+/// - Stations that fail validation are dropped, as with [`process_data`]
+///
+/// # Errors
+///
+/// - `TransformError::InvalidJson` if the input is not valid JSON
+/// - `TransformError::MissingLastUpdated` if `last_updated` is absent
+/// - `TransformError::InvalidTimestamp` if `last_updated` cannot be parsed
+/// - `TransformError::UnexpectedShape` if the document, `last_updated` or `stations`
+///   has the wrong JSON type
 ///
 /// # Examples
 ///
 /// ```rust
-/// let json = r#"{"last_updated": "2023-01-01T12:00:00Z", "stations": [...]}"#;
-/// let processed_stations = process_data(json);
+/// use refuel_radar_transform::{try_process_data, TransformError};
+///
+/// let result = try_process_data(r#"{"stations": []}"#);
+/// assert!(matches!(result, Err(TransformError::MissingLastUpdated)));
 /// ```
+pub fn try_process_data(json_data: &str) -> Result<Vec<StationPriceLastUpdated>, TransformError> {
+    process_feed(json_data).map(|feed| feed.stations)
+}
+
+/// Transforms a feed and reports on every station that was dropped along the way.
 ///
-/// # Potential Panics
+/// Behaves like [`try_process_data`], but also returns a [`FeedReport`] listing
+/// each rejected station entry (index, `site_id` if recoverable and the
+/// deserialization error) together with counts by reason.
 ///
-/// - Panics if JSON is invalid
-/// - Panics if timestamp parsing fails
-/// - Panics if station serialization fails
-pub fn process_data(json_data: &str) -> Vec<StationPriceLastUpdated> {
-    let data: FuelStationData = serde_json::from_str(json_data).expect("Invalid JSON");
-    // println!("=== data:\n{:#?}", data);
+/// # Errors
+///
+/// Fails with the same [`TransformError`] variants as [`try_process_data`]; a
+/// rejected station never fails the whole feed.
+///
+/// # Examples
+///
+/// ```rust
+/// use refuel_radar_transform::process_feed;
+///
+/// let json = r#"{"last_updated": "27/11/2024 11:45:32", "stations": [{"site_id": "a"}]}"#;
+/// let feed = process_feed(json).unwrap();
+/// assert!(feed.stations.is_empty());
+/// assert_eq!(feed.report.rejections[0].site_id.as_deref(), Some("a"));
+/// ```
+pub fn process_feed(json_data: &str) -> Result<ProcessedFeed, TransformError> {
+    process_feed_with(json_data, &TransformOptions::default())
+}
+
+/// Transforms a feed using the given [`TransformOptions`].
+///
+/// # Errors
+///
+/// Fails with the same [`TransformError`] variants as [`try_process_data`]
+pub fn process_feed_with(
+    json_data: &str,
+    options: &TransformOptions,
+) -> Result<ProcessedFeed, TransformError> {
     let FuelStationData {
         last_updated,
         stations,
-    } = data;
-
-    if !stations.is_empty() {
-        let last_updated_parsed = parse_datetime(&last_updated).unwrap();
-        let stations_json = serde_json::to_string(&stations).unwrap();
-        let processed_stations = process_stations(&stations_json);
-
-        let stations_with_last_updated: Vec<StationPriceLastUpdated> = processed_stations
-            .into_iter()
-            .map(|station| StationPriceLastUpdated {
-                site_id: station.site_id,
-                brand: station.brand,
-                address: station.address,
-                postcode: station.postcode,
-                location: station.location,
-                prices: vec![PriceLastUpdated {
-                    prices: station.prices,
-                    lu: last_updated_parsed.to_string(),
-                }],
-            })
-            .collect();
+    } = read_feed(json_data)?;
+
+    if stations.is_empty() {
+        return Ok(ProcessedFeed::default());
+    }
 
-        stations_with_last_updated
+    let (last_updated_parsed, timestamp_format) = parse_feed_timestamp(&last_updated, options)?;
+    let mut report = FeedReport {
+        timestamp_format: Some(timestamp_format),
+        ..FeedReport::default()
+    };
+    #[cfg(feature = "parallel")]
+    let transform = if stations.len() >= parallel::MIN_STATIONS {
+        parallel::transform_stations
     } else {
-        let nothing: Vec<StationPriceLastUpdated> = vec![];
-        nothing
+        transform_stations
+    };
+    #[cfg(not(feature = "parallel"))]
+    let transform = transform_stations;
+    let stations_with_last_updated =
+        transform(&stations, last_updated_parsed, options, &mut report);
+
+    Ok(ProcessedFeed {
+        stations: stations_with_last_updated,
+        report,
+    })
+}
+
+/// Runs every station of a feed through the transform, in feed order
+fn transform_stations(
+    stations: &[&RawValue],
+    feed_lu: DateTime<Utc>,
+    options: &TransformOptions,
+    report: &mut FeedReport,
+) -> Vec<StationPriceLastUpdated> {
+    let mut processed_stations = process_stations(stations, options, report);
+    for station in &mut processed_stations {
+        normalise_station(station, options, report);
     }
+    if let Some(price_units) = &options.price_units {
+        normalise_price_units(&mut processed_stations, price_units, report);
+    }
+
+    processed_stations
+        .into_iter()
+        .map(|station| finish_station(station, feed_lu, options, report))
+        .collect()
 }
 
-/// Processes JSON station data and extracts valid `StationPrices` entries.
+/// Parses a feed's `last_updated`, returning it with the format it matched
+fn parse_feed_timestamp(
+    last_updated: &str,
+    options: &TransformOptions,
+) -> Result<(DateTime<Utc>, String), TransformError> {
+    parse_timestamp(last_updated, &options.timestamp_formats, &options.timezone)
+        .map(|(lu, format)| (lu, format.to_string()))
+        .map_err(|source| TransformError::InvalidTimestamp {
+            value: last_updated.to_string(),
+            source,
+        })
+}
+
+/// Deserializes a feed document and checks its top-level shape.
+///
+/// Only the document structure is validated here; individual stations are
+/// validated later by [`process_stations`].
+fn read_feed(json_data: &str) -> Result<FuelStationData<'_>, TransformError> {
+    let mut root: HashMap<String, &RawValue> =
+        serde_json::from_str(json_data).map_err(|source| {
+            if !source.is_data() {
+                return TransformError::invalid_json(json_data, source);
+            }
+            // Valid JSON that is not an object
+            match serde_json::from_str::<&RawValue>(json_data) {
+                Ok(document) => TransformError::UnexpectedShape {
+                    path: "$".to_string(),
+                    expected: "object",
+                    found: json_type_name(document.get().as_bytes()),
+                },
+                Err(source) => TransformError::invalid_json(json_data, source),
+            }
+        })?;
+
+    let last_updated = match root.remove("last_updated") {
+        None => return Err(TransformError::MissingLastUpdated),
+        Some(raw) => {
+            serde_json::from_str(raw.get()).map_err(|_| TransformError::UnexpectedShape {
+                path: "last_updated".to_string(),
+                expected: "string",
+                found: json_type_name(raw.get().as_bytes()),
+            })?
+        }
+    };
+
+    let stations = match root.get("stations") {
+        Some(raw) => {
+            serde_json::from_str(raw.get()).map_err(|_| TransformError::UnexpectedShape {
+                path: "stations".to_string(),
+                expected: "array",
+                found: json_type_name(raw.get().as_bytes()),
+            })?
+        }
+        None => {
+            return Err(TransformError::UnexpectedShape {
+                path: "stations".to_string(),
+                expected: "array",
+                found: "nothing",
+            })
+        }
+    };
+
+    Ok(FuelStationData {
+        last_updated,
+        stations,
+    })
+}
+
+/// Deserializes each station of a feed into a `StationPrices` entry.
 ///
 /// This function performs the following operations:
-/// 1. Parses the input JSON string into a vector of JSON values
-/// 2. Attempts to convert each JSON value into a `StationPrices` struct
-/// 3. Filters out any conversion failures, returning only successfully parsed entries
+/// 1. Deserializes each unparsed station element directly into a `StationPrices` struct
+/// 2. Records every conversion failure in `report`, returning only successfully parsed entries
 ///
 /// # Parameters
 ///
-/// - `json_data`: A string slice containing JSON-formatted station data
+/// - `stations`: The unparsed elements of the feed's `stations` array
+/// - `options`: Settings for the checks run on each deserialized station
+/// - `report`: The feed report that rejections and the accepted count are added to
 ///
 /// # Returns
 ///
@@ -96,41 +284,249 @@ pub fn process_data(json_data: &str) -> Vec<StationPriceLastUpdated> {
 ///
 /// # Parsing Strategy
 ///
-/// - Uses `serde_json::from_str` to parse the JSON string
-/// - Falls back to an empty vector if initial parsing fails
-/// - Converts individual JSON values to `StationPrices` using `serde_json::from_value`
-/// - Filters out any entries that fail to convert
+/// - Each element is parsed once, so a bad element only rejects that station
+/// - Deserialized stations are then checked by [`check_station`]
+/// - Recovers the `site_id` of failed entries (when it is a string) for the report
 ///
 /// # Examples
 ///
-/// ```rust
+/// ```rust,ignore
 /// let json = r#"[{"id": 1, "name": "Station A"}, {"id": 2, "name": "Station B"}]"#;
-/// let stations = process_stations(json);
-/// assert!(!stations.is_empty());
+/// let stations: Vec<&RawValue> = serde_json::from_str(json).unwrap();
+/// let mut report = FeedReport::default();
+/// let stations = process_stations(&stations, &TransformOptions::default(), &mut report);
+/// assert_eq!(report.rejections.len(), 2);
 /// ```
+fn process_stations(
+    stations: &[&RawValue],
+    options: &TransformOptions,
+    report: &mut FeedReport,
+) -> Vec<StationPrices> {
+    let mut accepted = Vec::with_capacity(stations.len());
+
+    for (index, &raw) in stations.iter().enumerate() {
+        match StationPrices::deserialize(raw) {
+            Ok(station) => accepted.extend(check_station(station, index, options, report)),
+            Err(e) => reject_station(raw.get().as_bytes(), index, &e, report),
+        }
+    }
+
+    report.accepted = accepted.len();
+    accepted
+}
+
+/// Records a station element that failed to deserialize in `report`
+///
+/// The `site_id` is recovered from `json` when it is a string.
+fn reject_station(json: &[u8], index: usize, error: &serde_json::Error, report: &mut FeedReport) {
+    #[derive(Deserialize)]
+    struct SiteId {
+        site_id: Option<String>,
+    }
+
+    let site_id = serde_json::from_slice::<SiteId>(json)
+        .ok()
+        .and_then(|entry| entry.site_id);
+    report.reject(index, site_id, rejection_reason(error));
+}
+
+/// Checks a deserialized station, rejecting it if its location is unusable
 ///
-/// # Performance
+/// Coordinate forms are counted (and, if strict, restricted) with
+/// [`location::check_coordinate_form`]; swapped coordinates are repaired in
+/// place by [`location::check_location`].
+fn check_station(
+    mut station: StationPrices,
+    index: usize,
+    options: &TransformOptions,
+    report: &mut FeedReport,
+) -> Option<StationPrices> {
+    if let Err(reason) = check_coordinate_form(&station, options.strict_coordinates, report) {
+        report.reject(index, Some(station.site_id), reason);
+        return None;
+    }
+    if let Some(locations) = &options.locations {
+        if let Err(reason) = check_location(&mut station, locations, report) {
+            report.reject(index, Some(station.site_id), reason.to_string());
+            return None;
+        }
+    }
+    Some(station)
+}
+
+/// The message of a station deserialization error, without its position
 ///
-/// - Uses iterator-based processing for efficiency
-/// - Minimal memory overhead with `filter_map`
+/// Positions are relative to the station element rather than the feed, and
+/// would stop identical failures from being counted together.
+fn rejection_reason(error: &serde_json::Error) -> String {
+    let message = error.to_string();
+    let position = format!(" at line {} column {}", error.line(), error.column());
+    match message.strip_suffix(&position) {
+        Some(reason) => reason.to_string(),
+        None => message,
+    }
+}
+
+/// Applies the configurable normalisation steps to an accepted station.
 ///
-/// # Errors
+/// # Steps
 ///
-/// - Silently handles JSON parsing and conversion errors
-/// - Returns only successfully parsed `StationPrices` entries
-fn process_stations(json_data: &str) -> Vec<StationPrices> {
-    let result: Vec<Result<StationPrices, serde_json::Error>> =
-        serde_json::from_str::<Vec<serde_json::Value>>(json_data)
-            .unwrap_or_default()
-            .into_iter()
-            .map(serde_json::from_value::<StationPrices>)
-            .collect();
+/// - Re-formats the brand from the raw feed value using `options.brands`,
+///   recording brands that only matched fuzzily or are not in the table
+/// - Rewrites the postcode in canonical form, recording any that are not
+///   valid UK postcodes; see [`postcode::Postcode`]
+/// - Records price keys that did not parse to a known [`FuelType`]
+fn normalise_station(
+    station: &mut StationPrices,
+    options: &TransformOptions,
+    report: &mut FeedReport,
+) {
+    match options.brands.resolve(&station.raw_brand) {
+        Some(BrandMatch {
+            canonical,
+            kind: BrandMatchKind::Exact,
+        }) => station.brand = canonical.to_string(),
+        Some(BrandMatch { canonical, .. }) => {
+            station.brand = canonical.to_string();
+            report
+                .fuzzy_brand_matches
+                .insert(station.raw_brand.clone(), station.brand.clone());
+        }
+        None => {
+            station.brand = station.raw_brand.trim().to_string();
+            *report
+                .unmatched_brands
+                .entry(station.brand.clone())
+                .or_default() += 1;
+        }
+    }
+
+    normalise_postcode(station, report);
 
-    result.into_iter().filter_map(Result::ok).collect()
+    for fuel_type in station
+        .prices
+        .keys()
+        .filter(|fuel_type| fuel_type.is_other())
+    {
+        *report
+            .unrecognised_fuel_types
+            .entry(fuel_type.code().to_string())
+            .or_default() += 1;
+    }
+    for fuel_type in &station.duplicate_prices {
+        *report
+            .duplicate_fuel_types
+            .entry(fuel_type.code().to_string())
+            .or_default() += 1;
+    }
+}
+
+/// Converts a normalised station to its output form, grouping its prices by
+/// update time with [`price_snapshots`] and adding its grid reference and
+/// region when asked
+fn finish_station(
+    mut station: StationPrices,
+    feed_lu: DateTime<Utc>,
+    options: &TransformOptions,
+    report: &mut FeedReport,
+) -> StationPriceLastUpdated {
+    let prices = price_snapshots(&mut station, feed_lu, options, report);
+    station.location.grid = options.grid_references.then(|| {
+        let grid = station.location.grid_reference();
+        GridReference {
+            easting: grid.easting.round(),
+            northing: grid.northing.round(),
+        }
+    });
+    let region = options
+        .regions
+        .then(|| station.postcode.parse().ok().and_then(|p| classify(&p)))
+        .flatten();
+    StationPriceLastUpdated {
+        site_id: station.site_id,
+        brand: station.brand,
+        raw_brand: station.raw_brand,
+        address: station.address,
+        postcode: station.postcode,
+        location: station.location,
+        prices,
+        region,
+    }
+}
+
+/// Groups a station's prices by when each price was last updated.
+///
+/// # Timestamp Precedence
+///
+/// 1. The price's own update time, if the feed provides one
+/// 2. The station's update time, if the feed provides one
+/// 3. The feed's `last_updated`
+///
+/// Station and price timestamps are parsed like the feed's; any that cannot be
+/// parsed fall back to the next level and are recorded in `report.timestamp_issues`.
+///
+/// # Returns
+///
+/// One `PriceLastUpdated` per distinct update time, oldest first
+fn price_snapshots(
+    station: &mut StationPrices,
+    feed_lu: DateTime<Utc>,
+    options: &TransformOptions,
+    report: &mut FeedReport,
+) -> Vec<PriceLastUpdated> {
+    let site_id = &station.site_id;
+    let mut parse = |value: &str, fuel_type: Option<&FuelType>| {
+        parse_timestamp(value, &options.timestamp_formats, &options.timezone)
+            .map(|(lu, _)| lu)
+            .map_err(|e| {
+                report.timestamp_issues.push(TimestampIssue {
+                    site_id: site_id.clone(),
+                    fuel_type: fuel_type.cloned(),
+                    value: value.to_string(),
+                    reason: e.to_string(),
+                })
+            })
+            .ok()
+    };
+
+    let station_lu = station
+        .last_updated
+        .as_deref()
+        .and_then(|value| parse(value, None))
+        .unwrap_or(feed_lu);
+
+    let mut prices: Vec<(FuelType, f64)> =
+        std::mem::take(&mut station.prices).into_iter().collect();
+    prices.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    let mut snapshots: BTreeMap<DateTime<Utc>, PricesHashMap> = BTreeMap::new();
+    for (fuel_type, price) in prices {
+        let lu = station
+            .price_updated
+            .get(&fuel_type)
+            .and_then(|value| parse(value, Some(&fuel_type)))
+            .unwrap_or(station_lu);
+        snapshots.entry(lu).or_default().insert(fuel_type, price);
+    }
+    if snapshots.is_empty() {
+        snapshots.insert(station_lu, PricesHashMap::new());
+    }
+
+    snapshots
+        .into_iter()
+        .map(|(lu, prices)| PriceLastUpdated {
+            prices,
+            lu: lu.to_rfc3339(),
+        })
+        .collect()
 }
 
 /// Parses a datetime string into ISO 8601 format
 ///
+/// The timestamp is read as UTC, so feed times are an hour off during British
+/// Summer Time. Feeds publish London local time, which [`parse_datetime_in`]
+/// reads with the [`TimezoneOptions`] defaults.
+///
 /// # Arguments
 ///
 /// * `dt_str` - A datetime string in the format "dd/MM/yyyy HH:mm:ss"
@@ -138,6 +534,9 @@ fn process_stations(json_data: &str) -> Vec<StationPrices> {
 /// # Returns
 ///
 /// A `Result` containing the ISO 8601 formatted datetime string or a `ParseError`
+#[deprecated(
+    note = "reads feed times as UTC rather than London time; use `parse_datetime_in` with `TimezoneOptions::default()`"
+)]
 pub fn parse_datetime(dt_str: &str) -> Result<String, ParseError> {
     // Parse the input date format "dd/MM/yyyy HH:mm:ss"
     let naive_dt = NaiveDateTime::parse_from_str(dt_str, "%d/%m/%Y %H:%M:%S")?;
@@ -146,3 +545,404 @@ pub fn parse_datetime(dt_str: &str) -> Result<String, ParseError> {
     let utc_dt: DateTime<Utc> = DateTime::from_naive_utc_and_offset(naive_dt, Utc);
     Ok(utc_dt.to_rfc3339())
 }
+
+/// Parses a "dd/MM/yyyy HH:mm:ss" datetime string as local time in `timezone`
+///
+/// # Returns
+///
+/// A `Result` containing the UTC datetime or a `TimestampError`
+///
+/// # Examples
+///
+/// ```rust
+/// use refuel_radar_transform::parse_datetime_in;
+/// use refuel_radar_transform::timestamp::TimezoneOptions;
+///
+/// let utc = parse_datetime_in("01/07/2024 12:00:00", &TimezoneOptions::default()).unwrap();
+/// assert_eq!(utc.to_rfc3339(), "2024-07-01T11:00:00+00:00");
+/// ```
+pub fn parse_datetime_in(
+    dt_str: &str,
+    timezone: &TimezoneOptions,
+) -> Result<DateTime<Utc>, TimestampError> {
+    // Parse the input date format "dd/MM/yyyy HH:mm:ss"
+    let naive_dt = NaiveDateTime::parse_from_str(dt_str, "%d/%m/%Y %H:%M:%S")?;
+
+    local_to_utc(naive_dt, timezone)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::brand::BrandTable;
+    use crate::location::CoordinateForm;
+    use crate::postcode::PostcodeIssue;
+    use crate::station_struts::Location;
+
+    const FEED: &str = r#"{
+        "last_updated": "27/11/2024 11:45:32",
+        "stations": [
+            {
+                "site_id": "xxx",
+                "brand": "bp",
+                "address": "The Petrol station",
+                "postcode": "AB1 2CD",
+                "location": {
+                    "latitude": "51.5",
+                    "longitude": 0
+                },
+                "prices": {
+                    "E5": 138.9,
+                    "E10": 129.9,
+                    "B7": 138.9,
+                    "SDV": 0
+                }
+            },
+            {
+                "site_id": "yyy",
+                "brand": null,
+                "address": "Unbranded",
+                "postcode": "AB1 2CE",
+                "location": { "latitude": 51.6, "longitude": 0.1 },
+                "prices": { "E10": 130.9 }
+            }
+        ]
+    }"#;
+
+    #[test]
+    fn test_try_process_data() -> Result<(), TransformError> {
+        let stations = try_process_data(FEED)?;
+        assert_eq!(stations.len(), 1);
+        assert_eq!(stations[0].site_id, "xxx");
+        assert_eq!(stations[0].brand, "BP");
+        assert_eq!(stations[0].raw_brand, "bp");
+        assert_eq!(stations[0].prices[0].lu, "2024-11-27T11:45:32+00:00");
+
+        let feed = process_feed(&FEED.replace("27/11/2024 11:45:32", "2024-11-27T11:45:32Z"))?;
+        assert_eq!(feed.stations[0].prices[0].lu, "2024-11-27T11:45:32+00:00");
+        assert_eq!(feed.report.timestamp_format.as_deref(), Some("RFC 3339"));
+        assert!(!stations[0].prices[0].prices.contains_key(&FuelType::Sdv));
+        Ok(())
+    }
+
+    #[test]
+    fn test_rejection_report() -> Result<(), TransformError> {
+        let feed = process_feed(FEED)?;
+        assert_eq!(feed.report.accepted, 1);
+        assert_eq!(
+            feed.report.rejections,
+            vec![StationRejection {
+                index: 1,
+                site_id: Some("yyy".to_string()),
+                reason: "brand is null".to_string(),
+            }]
+        );
+        assert_eq!(feed.report.rejection_counts["brand is null"], 1);
+        assert!(feed.report.unmatched_brands.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_postcodes_normalised() -> Result<(), TransformError> {
+        let feed = process_feed(FEED)?;
+        assert_eq!(feed.stations[0].postcode, "AB1 2CD");
+        assert_eq!(
+            feed.report.postcode_issues,
+            vec![PostcodeIssue {
+                site_id: "xxx".to_string(),
+                postcode: "AB1 2CD".to_string(),
+            }]
+        );
+
+        let feed = process_feed(&FEED.replace("AB1 2CD", " ab101aa"))?;
+        assert_eq!(feed.stations[0].postcode, "AB10 1AA");
+        assert!(feed.report.postcode_issues.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_station_regions() -> Result<(), TransformError> {
+        let json = FEED.replace("AB1 2CD", "td15 1ba");
+        let feed = process_feed(&json)?;
+        assert_eq!(feed.stations[0].region, None);
+        assert!(serde_json::to_value(&feed.stations[0])
+            .unwrap()
+            .get("region")
+            .is_none());
+
+        let options = TransformOptions {
+            regions: true,
+            ..TransformOptions::default()
+        };
+        let feed = process_feed_with(&json, &options)?;
+        let region = feed.stations[0].region.as_ref().unwrap();
+        assert_eq!(region.area, "TD");
+        assert_eq!(region.ons_region, region::OnsRegion::NorthEast);
+        assert_eq!(region.country, region::Country::England);
+
+        let feed = process_feed_with(FEED, &options)?;
+        assert_eq!(feed.stations[0].region, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_custom_brand_table() -> Result<(), TransformError> {
+        let options = TransformOptions {
+            brands: BrandTable::empty().with_brand("B.P.", ["bp"]),
+            ..TransformOptions::default()
+        };
+        let feed = process_feed_with(FEED, &options)?;
+        assert_eq!(feed.stations[0].brand, "B.P.");
+
+        let options = TransformOptions {
+            brands: BrandTable::empty(),
+            ..TransformOptions::default()
+        };
+        let feed = process_feed_with(FEED, &options)?;
+        assert_eq!(feed.stations[0].brand, "bp");
+        assert_eq!(feed.report.unmatched_brands["bp"], 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_fuel_type_aliases() -> Result<(), TransformError> {
+        let json = r#"{
+            "last_updated": "27/11/2024 11:45:32",
+            "stations": [{
+                "site_id": "a",
+                "brand": "bp",
+                "address": "1 High Street",
+                "postcode": "AB1 2CD",
+                "location": { "latitude": 51.5, "longitude": -0.1 },
+                "prices": { "e10": 130.9, "E10 ": 131.9, "unleaded": 132.9, "Diesel": "139.9", "LPG": 89.9 }
+            }]
+        }"#;
+        let feed = process_feed(json)?;
+        let prices = &feed.stations[0].prices[0].prices;
+        assert_eq!(prices.len(), 3);
+        assert_eq!(prices[&FuelType::E10], 131.9);
+        assert_eq!(prices[&FuelType::B7], 139.9);
+        assert_eq!(feed.report.unrecognised_fuel_types["LPG"], 1);
+        assert_eq!(feed.report.duplicate_fuel_types["E10"], 1);
+
+        let output = serde_json::to_value(&feed.stations[0].prices[0]).unwrap();
+        assert_eq!(output["E10"], 131.9);
+        assert_eq!(output["LPG"], 89.9);
+        Ok(())
+    }
+
+    #[test]
+    fn test_price_units_normalised_to_pence() -> Result<(), TransformError> {
+        let json = r#"{
+            "last_updated": "27/11/2024 11:45:32",
+            "stations": [{
+                "site_id": "pounds",
+                "brand": "bp",
+                "address": "1 High Street",
+                "postcode": "AB1 2CD",
+                "location": { "latitude": 51.5, "longitude": -0.1 },
+                "prices": { "E10": 1.389, "B7": 1.459, "E5": 12.5 }
+            }, {
+                "site_id": "tenths",
+                "brand": "bp",
+                "address": "2 High Street",
+                "postcode": "AB1 2CE",
+                "location": { "latitude": 51.5, "longitude": -0.1 },
+                "prices": { "E10": 1389 }
+            }]
+        }"#;
+        let feed = process_feed(json)?;
+        assert_eq!(feed.stations[0].prices[0].prices[&FuelType::E10], 138.9);
+        assert_eq!(feed.stations[0].prices[0].prices[&FuelType::B7], 145.9);
+        assert!(!feed.stations[0].prices[0]
+            .prices
+            .contains_key(&FuelType::E5));
+        assert_eq!(feed.stations[1].prices[0].prices[&FuelType::E10], 138.9);
+        assert_eq!(feed.report.price_flags.len(), 1);
+        assert_eq!(feed.report.price_flags[0].value, 12.5);
+
+        let options = TransformOptions {
+            price_units: None,
+            ..TransformOptions::default()
+        };
+        let feed = process_feed_with(json, &options)?;
+        assert_eq!(feed.stations[0].prices[0].prices[&FuelType::E10], 1.389);
+        Ok(())
+    }
+
+    #[test]
+    fn test_last_updated_is_london_time() -> Result<(), TransformError> {
+        let summer_feed = FEED.replace("27/11/2024", "27/07/2024");
+        let stations = try_process_data(&summer_feed)?;
+        assert_eq!(stations[0].prices[0].lu, "2024-07-27T10:45:32+00:00");
+
+        let options = TransformOptions {
+            timezone: TimezoneOptions::in_timezone(chrono_tz::UTC),
+            ..TransformOptions::default()
+        };
+        let feed = process_feed_with(&summer_feed, &options)?;
+        assert_eq!(feed.stations[0].prices[0].lu, "2024-07-27T11:45:32+00:00");
+
+        #[allow(deprecated)]
+        let utc = parse_datetime("27/07/2024 11:45:32");
+        assert_eq!(utc.unwrap(), "2024-07-27T11:45:32+00:00");
+        let london = parse_datetime_in("27/07/2024 11:45:32", &TimezoneOptions::default());
+        assert_eq!(london.unwrap().to_rfc3339(), "2024-07-27T10:45:32+00:00");
+        Ok(())
+    }
+
+    #[test]
+    fn test_station_and_price_timestamps() -> Result<(), TransformError> {
+        let json = r#"{
+            "last_updated": "27/11/2024 12:00:00",
+            "stations": [{
+                "site_id": "a",
+                "brand": "bp",
+                "address": "1 High Street",
+                "postcode": "AB1 2CD",
+                "location": { "latitude": 51.5, "longitude": -0.1 },
+                "last_updated": "27/11/2024 10:00:00",
+                "prices": {
+                    "E10": { "price": 131.9, "last_updated": "27/11/2024 08:00:00" },
+                    "B7": { "price": 139.9, "last_updated": "never" },
+                    "E5": 145.9
+                }
+            }, {
+                "site_id": "b",
+                "brand": "bp",
+                "address": "2 High Street",
+                "postcode": "AB1 2CE",
+                "location": { "latitude": 51.5, "longitude": -0.1 },
+                "prices": { "E10": 132.9 }
+            }]
+        }"#;
+        let feed = process_feed(json)?;
+
+        let a = &feed.stations[0].prices;
+        assert_eq!(a.len(), 2);
+        assert_eq!(a[0].lu, "2024-11-27T08:00:00+00:00");
+        assert_eq!(a[0].prices[&FuelType::E10], 131.9);
+        assert_eq!(a[1].lu, "2024-11-27T10:00:00+00:00");
+        assert_eq!(a[1].prices.len(), 2);
+
+        assert_eq!(feed.stations[1].prices[0].lu, "2024-11-27T12:00:00+00:00");
+        assert_eq!(feed.report.timestamp_issues.len(), 1);
+        assert_eq!(
+            feed.report.timestamp_issues[0].fuel_type,
+            Some(FuelType::B7)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_non_string_station_timestamps_are_ignored() -> Result<(), TransformError> {
+        let station = |site_id: &str, timestamps: &str| {
+            format!(
+                r#"{{"site_id": "{site_id}", "brand": "bp", "address": "", "postcode": "",
+                    "location": {{"latitude": 51.5, "longitude": -0.1}},
+                    "prices": {{"E10": 131.9}}, {timestamps}}}"#
+            )
+        };
+        let json = format!(
+            r#"{{"last_updated": "27/11/2024 12:00:00", "stations": [{}, {}, {}]}}"#,
+            station("a", r#""last_updated": 1732700000"#),
+            station("b", r#""updated_at": {"date": "27/11/2024"}"#),
+            station(
+                "c",
+                r#""last_updated": 1732700000, "lu": "27/11/2024 10:00:00""#
+            ),
+        );
+        let feed = process_feed(&json)?;
+
+        assert_eq!(feed.report.accepted, 3);
+        assert_eq!(feed.stations[0].prices[0].lu, "2024-11-27T12:00:00+00:00");
+        assert_eq!(feed.stations[1].prices[0].lu, "2024-11-27T12:00:00+00:00");
+        assert_eq!(feed.stations[2].prices[0].lu, "2024-11-27T10:00:00+00:00");
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_json_reports_offset() {
+        let input = "{\n  \"last_updated\": ,\n}";
+        match try_process_data(input) {
+            Err(TransformError::InvalidJson {
+                line,
+                column,
+                offset,
+                ..
+            }) => {
+                assert_eq!((line, column), (2, 19));
+                assert_eq!(&input[offset..offset + 1], ",");
+            }
+            other => panic!("expected InvalidJson, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_shape_errors() {
+        assert!(matches!(
+            try_process_data(r#"{"stations": [{}]}"#),
+            Err(TransformError::MissingLastUpdated)
+        ));
+        assert!(matches!(
+            try_process_data(r#"{"last_updated": "27/11/2024 11:45:32", "stations": {}}"#),
+            Err(TransformError::UnexpectedShape { path, found: "object", .. }) if path == "stations"
+        ));
+        assert!(matches!(
+            try_process_data("[]"),
+            Err(TransformError::UnexpectedShape { path, .. }) if path == "$"
+        ));
+        assert!(matches!(
+            try_process_data(r#"{"last_updated": "27/11/2024", "stations": [{}]}"#),
+            Err(TransformError::InvalidTimestamp { value, .. }) if value == "27/11/2024"
+        ));
+    }
+
+    #[test]
+    fn test_grid_reference_locations() -> Result<(), TransformError> {
+        let json = FEED
+            .replace(
+                r#""latitude": 51.6, "longitude": 0.1"#,
+                r#""easting": 530268"#,
+            )
+            .replace(r#""latitude": "51.5""#, r#""easting": "530268""#)
+            .replace(r#""longitude": 0"#, r#""northing": 179640"#);
+        let feed = process_feed(&json)?;
+        let location = &feed.stations[0].location;
+        assert!((location.latitude() - 51.5).abs() < 0.01);
+        assert!((location.longitude() + 0.12).abs() < 0.01);
+        assert_eq!(location.form(), CoordinateForm::String);
+        assert_eq!(feed.report.rejections[0].reason, "missing field `northing`");
+
+        for (easting, reason) in [
+            ("-5", "grid reference is not on the British National Grid"),
+            ("\"east\"", "Invalid grid coordinate"),
+        ] {
+            let json = json.replace(
+                r#""easting": "530268""#,
+                &format!(r#""easting": {easting}"#),
+            );
+            let feed = process_feed(&json)?;
+            assert_eq!(feed.report.rejections[0].reason, reason);
+        }
+
+        let output = serde_json::to_value(location).unwrap();
+        assert!(output.get("easting").is_none());
+
+        // A grid reference given alongside latitude and longitude is not kept
+        let both = r#"{ "latitude": 51.5, "longitude": 0, "easting": -5, "northing": 0 }"#;
+        let location: Location = serde_json::from_str(both).unwrap();
+        assert!(location.grid.is_none());
+
+        let options = TransformOptions {
+            grid_references: true,
+            ..TransformOptions::default()
+        };
+        let feed = process_feed_with(FEED, &options)?;
+        let output = serde_json::to_value(&feed.stations[0].location).unwrap();
+        let grid = GridReference::from_wgs84(51.5, 0.0);
+        assert_eq!(output["easting"], grid.easting.round());
+        assert_eq!(output["northing"], grid.northing.round());
+        Ok(())
+    }
+}
@@ -0,0 +1,450 @@
+use serde::Serialize;
+
+use crate::report::FeedReport;
+use crate::station_struts::StationPrices;
+
+/// How a coordinate was written in a feed, from most to least plain
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+pub enum CoordinateForm {
+    /// A JSON number, e.g. `51.5123`
+    #[default]
+    Number,
+    /// A plain numeric string, e.g. `"51.5123"`
+    String,
+    /// Decimal degrees with surrounding whitespace, a degree symbol or a
+    /// hemisphere letter, e.g. `" 51.5123° N"`
+    Decorated,
+    /// Decimal degrees with a comma as the decimal separator, e.g. `"51,5123"`
+    DecimalComma,
+    /// Degrees, minutes and optionally seconds, e.g. `"51°30'44.3\"N"` or `"51 30 44.3 N"`
+    Dms,
+}
+
+impl CoordinateForm {
+    fn description(self) -> &'static str {
+        match self {
+            CoordinateForm::Number => "number",
+            CoordinateForm::String => "numeric string",
+            CoordinateForm::Decorated => "decorated",
+            CoordinateForm::DecimalComma => "decimal comma",
+            CoordinateForm::Dms => "degrees-minutes-seconds",
+        }
+    }
+}
+
+/// Which coordinate a value is, so a hemisphere letter can be checked against it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Axis {
+    /// Takes `N` or `S`
+    Latitude,
+    /// Takes `E` or `W`
+    Longitude,
+}
+
+/// Symbols that may follow degrees, minutes or seconds
+const DMS_SYMBOLS: [char; 6] = ['°', 'º', '\'', '′', '"', '″'];
+
+/// Parses a coordinate string in any of the [`CoordinateForm`]s.
+///
+/// # Parsing
+///
+/// - Surrounding whitespace is ignored
+/// - A leading or trailing `N` or `S` on a latitude, or `E` or `W` on a
+///   longitude, sets the sign, with `S` and `W` negative; a `-` that
+///   contradicts an `N` or `E`, or a letter for the other axis, is rejected
+/// - Degrees, minutes and seconds may be separated by whitespace and/or
+///   symbols (`°`, `'`, `"`, `′`, `″`); minutes and seconds must be under 60
+/// - Any component may use a decimal comma instead of a point
+///
+/// Returns `None` for anything else, including infinite and NaN values.
+///
+/// # Examples
+///
+/// ```rust
+/// use refuel_radar_transform::location::{parse_coordinate, Axis, CoordinateForm};
+///
+/// let latitude = |value| parse_coordinate(value, Axis::Latitude);
+/// assert_eq!(latitude("51,5"), Some((51.5, CoordinateForm::DecimalComma)));
+/// assert_eq!(latitude("51°30'36\"N"), Some((51.51, CoordinateForm::Dms)));
+/// assert_eq!(latitude("51.5 W"), None);
+/// assert_eq!(latitude("north"), None);
+/// assert_eq!(
+///     parse_coordinate("0.1° W", Axis::Longitude),
+///     Some((-0.1, CoordinateForm::Decorated))
+/// );
+/// ```
+pub fn parse_coordinate(value: &str, axis: Axis) -> Option<(f64, CoordinateForm)> {
+    let trimmed = value.trim();
+    if let Ok(number) = trimmed.parse::<f64>() {
+        if !number.is_finite() {
+            return None;
+        }
+        let form = if trimmed.len() == value.len() {
+            CoordinateForm::String
+        } else {
+            CoordinateForm::Decorated
+        };
+        return Some((number, form));
+    }
+
+    let is_hemisphere = |c: char| matches!(c.to_ascii_uppercase(), 'N' | 'S' | 'E' | 'W');
+    let (rest, hemisphere) = match (trimmed.chars().next(), trimmed.chars().last()) {
+        (_, Some(last)) if is_hemisphere(last) => (&trimmed[..trimmed.len() - 1], Some(last)),
+        (Some(first), _) if is_hemisphere(first) => (&trimmed[1..], Some(first)),
+        _ => (trimmed, None),
+    };
+
+    let parts: Vec<&str> = rest
+        .split(|c: char| c.is_whitespace() || DMS_SYMBOLS.contains(&c))
+        .filter(|part| !part.is_empty())
+        .collect();
+    let mut decimal_comma = false;
+    let mut parse_part = |part: &str| {
+        if part.contains(',') && !part.contains('.') {
+            decimal_comma = true;
+            part.replacen(',', ".", 1).parse::<f64>().ok()
+        } else {
+            part.parse::<f64>().ok()
+        }
+    };
+
+    let (degrees, form) = match parts.as_slice() {
+        [degrees] => (parse_part(degrees)?, CoordinateForm::Decorated),
+        [degrees, rest @ ..] if rest.len() <= 2 => {
+            let degrees = parse_part(degrees)?;
+            let mut total = degrees.abs();
+            for (part, divisor) in rest.iter().zip([60.0, 3600.0]) {
+                let value = parse_part(part)?;
+                if !(0.0..60.0).contains(&value) {
+                    return None;
+                }
+                total += value / divisor;
+            }
+            (total.copysign(degrees), CoordinateForm::Dms)
+        }
+        _ => return None,
+    };
+
+    let form = if decimal_comma {
+        form.max(CoordinateForm::DecimalComma)
+    } else {
+        form
+    };
+    let value = match (hemisphere.map(|c| c.to_ascii_uppercase()), axis) {
+        (Some('E' | 'W'), Axis::Latitude) | (Some('N' | 'S'), Axis::Longitude) => return None,
+        (Some('S' | 'W'), _) => -degrees.abs(),
+        (Some(_), _) if degrees.is_sign_negative() => return None,
+        _ => degrees,
+    };
+    value.is_finite().then_some((value, form))
+}
+
+/// Counts the form a station's coordinates were written in, rejecting
+/// anything but numbers and plain numeric strings when `strict`
+pub(crate) fn check_coordinate_form(
+    station: &StationPrices,
+    strict: bool,
+    report: &mut FeedReport,
+) -> Result<(), String> {
+    let form = station.location.form;
+    *report.coordinate_forms.entry(form).or_default() += 1;
+    if strict && form > CoordinateForm::String {
+        return Err(format!(
+            "coordinates in {} form are not accepted in strict mode",
+            form.description()
+        ));
+    }
+    Ok(())
+}
+
+/// An inclusive latitude/longitude box that station locations must fall in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingRegion {
+    pub min_latitude: f64,
+    pub max_latitude: f64,
+    pub min_longitude: f64,
+    pub max_longitude: f64,
+}
+
+impl BoundingRegion {
+    /// Great Britain, Northern Ireland, the Channel Islands and the Isle of Man
+    pub const UK: BoundingRegion = BoundingRegion {
+        min_latitude: 49.0,
+        max_latitude: 61.0,
+        min_longitude: -8.7,
+        max_longitude: 2.0,
+    };
+
+    pub fn contains(&self, latitude: f64, longitude: f64) -> bool {
+        (self.min_latitude..=self.max_latitude).contains(&latitude)
+            && (self.min_longitude..=self.max_longitude).contains(&longitude)
+    }
+}
+
+impl Default for BoundingRegion {
+    fn default() -> Self {
+        BoundingRegion::UK
+    }
+}
+
+/// Settings for validating and repairing station locations
+///
+/// # Defaults
+///
+/// - Locations must be in [`BoundingRegion::UK`]
+/// - Swapped latitude and longitude are repaired
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocationOptions {
+    pub region: BoundingRegion,
+    /// Swaps latitude and longitude back when only the swapped location is in `region`
+    pub repair_swapped: bool,
+}
+
+impl Default for LocationOptions {
+    fn default() -> Self {
+        LocationOptions {
+            region: BoundingRegion::default(),
+            repair_swapped: true,
+        }
+    }
+}
+
+/// What was wrong with a station's location
+///
+/// Non-finite coordinates never reach these checks: they are rejected while
+/// the station is read, with the reason "Invalid coordinate"
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum LocationProblem {
+    /// The location is at (or within 0.01° of) latitude 0, longitude 0
+    NullIsland,
+    /// Latitude and longitude are the wrong way round
+    Swapped,
+    /// The location is outside the bounding region
+    OutsideRegion,
+}
+
+impl LocationProblem {
+    /// The rejection reason recorded for a station with this problem
+    fn reason(self) -> &'static str {
+        match self {
+            LocationProblem::NullIsland => "location is null island (0, 0)",
+            LocationProblem::Swapped => "latitude and longitude are swapped",
+            LocationProblem::OutsideRegion => "location is outside the bounding region",
+        }
+    }
+}
+
+/// A station location that was repaired or rejected
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LocationIssue {
+    pub site_id: String,
+    /// The latitude as published in the feed
+    pub latitude: f64,
+    /// The longitude as published in the feed
+    pub longitude: f64,
+    pub problem: LocationProblem,
+    /// `true` if the location was fixed, `false` if the station was rejected
+    pub repaired: bool,
+}
+
+/// Checks a station's location, repairing it if possible.
+///
+/// Every repair and every unrepairable location is recorded in
+/// `report.location_issues`.
+///
+/// # Errors
+///
+/// Returns the rejection reason when the location cannot be repaired.
+pub(crate) fn check_location(
+    station: &mut StationPrices,
+    options: &LocationOptions,
+    report: &mut FeedReport,
+) -> Result<(), &'static str> {
+    let latitude = station.location.latitude;
+    let longitude = station.location.longitude;
+
+    let problem = if latitude.abs() < 0.01 && longitude.abs() < 0.01 {
+        LocationProblem::NullIsland
+    } else if options.region.contains(latitude, longitude) {
+        return Ok(());
+    } else if options.region.contains(longitude, latitude) {
+        LocationProblem::Swapped
+    } else {
+        LocationProblem::OutsideRegion
+    };
+
+    let repaired = problem == LocationProblem::Swapped && options.repair_swapped;
+    if repaired {
+        station.location.latitude = longitude;
+        station.location.longitude = latitude;
+    }
+    report.location_issues.push(LocationIssue {
+        site_id: station.site_id.clone(),
+        latitude,
+        longitude,
+        problem,
+        repaired,
+    });
+
+    if repaired {
+        Ok(())
+    } else {
+        Err(problem.reason())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::{process_feed, process_feed_with, TransformOptions};
+
+    #[test]
+    fn test_locations_are_repaired_or_rejected() {
+        let station = |site_id: &str, latitude: &str, longitude: &str| {
+            format!(
+                r#"{{"site_id": "{site_id}", "brand": "bp", "address": "", "postcode": "",
+                    "location": {{ "latitude": {latitude}, "longitude": {longitude} }}, "prices": {{}}}}"#
+            )
+        };
+        let json = format!(
+            r#"{{"last_updated": "27/11/2024 11:45:32", "stations": [{}]}}"#,
+            [
+                station("ok", "51.5", "-0.1"),
+                station("swapped", "-0.1", "51.5"),
+                station("null", "0", "0"),
+                station("atlantic", "40.0", "-30.0"),
+                station("nan", "\"NaN\"", "-0.1"),
+                station("text", "\"abc\"", "-0.1"),
+            ]
+            .join(",")
+        );
+        let feed = process_feed(&json).unwrap();
+
+        assert_eq!(feed.stations.len(), 2);
+        assert_eq!(feed.stations[1].site_id, "swapped");
+        assert_eq!(feed.stations[1].location.latitude, 51.5);
+        assert_eq!(feed.stations[1].location.longitude, -0.1);
+
+        let issues: Vec<(&str, LocationProblem, bool)> = feed
+            .report
+            .location_issues
+            .iter()
+            .map(|issue| (issue.site_id.as_str(), issue.problem, issue.repaired))
+            .collect();
+        assert_eq!(
+            issues,
+            [
+                ("swapped", LocationProblem::Swapped, true),
+                ("null", LocationProblem::NullIsland, false),
+                ("atlantic", LocationProblem::OutsideRegion, false),
+            ]
+        );
+        assert_eq!(feed.report.rejections[0].index, 2);
+        assert_eq!(
+            feed.report.rejection_counts["location is null island (0, 0)"],
+            1
+        );
+        // Unparseable coordinates share one reason, whatever their value
+        assert_eq!(feed.report.rejection_counts["Invalid coordinate"], 2);
+    }
+
+    #[test]
+    fn test_parse_coordinate_forms() {
+        let (lat, lon) = (Axis::Latitude, Axis::Longitude);
+        let cases = [
+            ("51.5", lat, 51.5, CoordinateForm::String),
+            ("-0.25 ", lon, -0.25, CoordinateForm::Decorated),
+            ("51.5°", lat, 51.5, CoordinateForm::Decorated),
+            ("W 0.25", lon, -0.25, CoordinateForm::Decorated),
+            ("-0,25", lon, -0.25, CoordinateForm::DecimalComma),
+            (" 51,5° N", lat, 51.5, CoordinateForm::DecimalComma),
+            ("51° 30′ 36″ N", lat, 51.51, CoordinateForm::Dms),
+            ("0 15 W", lon, -0.25, CoordinateForm::Dms),
+            ("-0°15'", lon, -0.25, CoordinateForm::Dms),
+            ("51 30,6", lat, 51.51, CoordinateForm::Dms),
+            ("-0.25 W", lon, -0.25, CoordinateForm::Decorated),
+            ("S -33.9", lat, -33.9, CoordinateForm::Decorated),
+        ];
+        for (value, axis, expected, form) in cases {
+            let (parsed, parsed_form) = parse_coordinate(value, axis).unwrap();
+            assert!((parsed - expected).abs() < 1e-9, "{value}: {parsed}");
+            assert_eq!(parsed_form, form, "{value}");
+        }
+
+        for (value, axis) in [
+            ("", lat),
+            ("N", lat),
+            ("51 60", lat),
+            ("51 30 15 5", lat),
+            ("51.5.5", lat),
+            ("fifty", lat),
+            ("-0.25 E", lon),
+            ("-51.5 N", lat),
+            ("inf", lat),
+            ("-infinity", lon),
+            ("NaN", lat),
+            ("1e400", lat),
+            ("NaN N", lat),
+            ("1e400° W", lon),
+            ("51.5 W", lat),
+            ("E 51.5", lat),
+            ("0.25 N", lon),
+            ("0 15 S", lon),
+        ] {
+            assert_eq!(parse_coordinate(value, axis), None, "{value}");
+        }
+    }
+
+    #[test]
+    fn test_strict_coordinates() {
+        let json = r#"{"last_updated": "27/11/2024 11:45:32", "stations": [{
+            "site_id": "a", "brand": "bp", "address": "", "postcode": "",
+            "location": { "latitude": "51,5", "longitude": -0.1 }, "prices": {}
+        }, {
+            "site_id": "b", "brand": "bp", "address": "", "postcode": "",
+            "location": { "latitude": "51.5", "longitude": -0.1 }, "prices": {}
+        }]}"#;
+
+        let feed = process_feed(json).unwrap();
+        assert_eq!(feed.stations[0].location.latitude, 51.5);
+        assert_eq!(
+            feed.report.coordinate_forms,
+            BTreeMap::from([
+                (CoordinateForm::String, 1),
+                (CoordinateForm::DecimalComma, 1)
+            ])
+        );
+
+        let options = TransformOptions {
+            strict_coordinates: true,
+            ..TransformOptions::default()
+        };
+        let feed = process_feed_with(json, &options).unwrap();
+        assert_eq!(feed.stations.len(), 1);
+        assert_eq!(
+            feed.report.rejections[0].reason,
+            "coordinates in decimal comma form are not accepted in strict mode"
+        );
+    }
+
+    #[test]
+    fn test_swapped_repair_can_be_disabled() {
+        let options = LocationOptions {
+            repair_swapped: false,
+            ..LocationOptions::default()
+        };
+        let mut report = FeedReport::default();
+        let mut station: StationPrices = serde_json::from_str(
+            r#"{"site_id": "a", "brand": "bp", "address": "", "postcode": "",
+                "location": { "latitude": -0.1, "longitude": 51.5 }, "prices": {}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            check_location(&mut station, &options, &mut report),
+            Err("latitude and longitude are swapped")
+        );
+        assert!(!report.location_issues[0].repaired);
+    }
+}
@@ -0,0 +1,62 @@
+use crate::brand::BrandTable;
+use crate::location::LocationOptions;
+use crate::timestamp::{TimestampFormat, TimezoneOptions};
+use crate::units::PriceUnitOptions;
+
+/// Settings that control how feeds are transformed
+///
+/// `TransformOptions::default()` reproduces the behaviour of [`crate::process_data`]:
+/// the built-in brand table, price normalisation to pence, locations checked
+/// against the UK and timestamps in any of the [`TimestampFormat::defaults`],
+/// read as Europe/London local time.
+///
+/// # Examples
+///
+/// ```rust
+/// use refuel_radar_transform::brand::BrandTable;
+/// use refuel_radar_transform::{process_feed_with, TransformOptions};
+///
+/// let options = TransformOptions {
+///     brands: BrandTable::default().with_brand("Valero", ["valero uk"]),
+///     ..TransformOptions::default()
+/// };
+/// let json = r#"{"last_updated": "27/11/2024 11:45:32", "stations": []}"#;
+/// assert!(process_feed_with(json, &options).is_ok());
+/// ```
+#[derive(Debug, Clone)]
+pub struct TransformOptions {
+    /// Alias table used to normalise station brands
+    pub brands: BrandTable,
+    /// Price unit detection settings; `None` passes prices through unchanged
+    pub price_units: Option<PriceUnitOptions>,
+    /// Location validation settings; `None` accepts any coordinates
+    pub locations: Option<LocationOptions>,
+    /// Rejects stations with coordinates that are not numbers or plain numeric
+    /// strings, instead of parsing them; see [`crate::location::CoordinateForm`]
+    pub strict_coordinates: bool,
+    /// Adds each station's British National Grid easting and northing to its
+    /// location, rounded to the metre; see [`crate::bng::GridReference`]
+    pub grid_references: bool,
+    /// Adds each station's postcode area, ONS region and country; see
+    /// [`crate::region::classify`]
+    pub regions: bool,
+    /// Timezone the feed's `last_updated` is published in
+    pub timezone: TimezoneOptions,
+    /// Formats tried, in order, when parsing `last_updated`
+    pub timestamp_formats: Vec<TimestampFormat>,
+}
+
+impl Default for TransformOptions {
+    fn default() -> Self {
+        TransformOptions {
+            brands: BrandTable::default(),
+            price_units: Some(PriceUnitOptions::default()),
+            locations: Some(LocationOptions::default()),
+            strict_coordinates: false,
+            grid_references: false,
+            regions: false,
+            timezone: TimezoneOptions::default(),
+            timestamp_formats: TimestampFormat::defaults(),
+        }
+    }
+}
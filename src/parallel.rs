@@ -0,0 +1,220 @@
+use chrono::{DateTime, Utc};
+use rayon::prelude::*;
+use serde::Deserialize;
+use serde_json::value::RawValue;
+
+use crate::options::TransformOptions;
+use crate::report::FeedReport;
+use crate::station_struts::{StationPriceLastUpdated, StationPrices};
+use crate::units::normalise_price_units;
+use crate::{check_station, finish_station, normalise_station, reject_station};
+
+/// Feeds with fewer stations than this are transformed sequentially, as
+/// splitting them across threads costs more than it saves
+pub(crate) const MIN_STATIONS: usize = 1024;
+
+/// Runs every station of a feed through the transform across all cores.
+///
+/// Each station is given its own report, and the reports are merged in feed
+/// order, so the stations and report are identical to those of
+/// [`transform_stations`](crate::transform_stations). Price unit detection
+/// runs sequentially between the two parallel steps, as per-feed detection
+/// needs every station's prices.
+pub(crate) fn transform_stations(
+    stations: &[&RawValue],
+    feed_lu: DateTime<Utc>,
+    options: &TransformOptions,
+    report: &mut FeedReport,
+) -> Vec<StationPriceLastUpdated> {
+    let read: Vec<(Option<StationPrices>, FeedReport)> = stations
+        .par_iter()
+        .enumerate()
+        .map(|(index, &raw)| {
+            let mut station_report = FeedReport::default();
+            let station = match StationPrices::deserialize(raw) {
+                Ok(station) => check_station(station, index, options, &mut station_report).map(
+                    |mut station| {
+                        normalise_station(&mut station, options, &mut station_report);
+                        station
+                    },
+                ),
+                Err(e) => {
+                    reject_station(raw.get().as_bytes(), index, &e, &mut station_report);
+                    None
+                }
+            };
+            (station, station_report)
+        })
+        .collect();
+
+    let mut accepted = Vec::with_capacity(read.len());
+    for (station, station_report) in read {
+        merge_report(report, station_report);
+        accepted.extend(station);
+    }
+    report.accepted = accepted.len();
+    if let Some(price_units) = &options.price_units {
+        normalise_price_units(&mut accepted, price_units, report);
+    }
+
+    let finished: Vec<(StationPriceLastUpdated, FeedReport)> = accepted
+        .into_par_iter()
+        .map(|station| {
+            let mut station_report = FeedReport::default();
+            let station = finish_station(station, feed_lu, options, &mut station_report);
+            (station, station_report)
+        })
+        .collect();
+
+    finished
+        .into_iter()
+        .map(|(station, station_report)| {
+            merge_report(report, station_report);
+            station
+        })
+        .collect()
+}
+
+/// Adds the findings of a single station's report to a feed's report
+fn merge_report(report: &mut FeedReport, other: FeedReport) {
+    let FeedReport {
+        timestamp_format: _,
+        accepted: _,
+        rejections,
+        rejection_counts,
+        unmatched_brands,
+        fuzzy_brand_matches,
+        unrecognised_fuel_types,
+        duplicate_fuel_types,
+        price_units,
+        price_flags,
+        timestamp_issues,
+        coordinate_forms,
+        location_issues,
+        postcode_issues,
+    } = other;
+
+    report.rejections.extend(rejections);
+    for (reason, count) in rejection_counts {
+        *report.rejection_counts.entry(reason).or_default() += count;
+    }
+    for (brand, count) in unmatched_brands {
+        *report.unmatched_brands.entry(brand).or_default() += count;
+    }
+    report.fuzzy_brand_matches.extend(fuzzy_brand_matches);
+    for (fuel_type, count) in unrecognised_fuel_types {
+        *report.unrecognised_fuel_types.entry(fuel_type).or_default() += count;
+    }
+    for (fuel_type, count) in duplicate_fuel_types {
+        *report.duplicate_fuel_types.entry(fuel_type).or_default() += count;
+    }
+    for (unit, count) in price_units {
+        *report.price_units.entry(unit).or_default() += count;
+    }
+    report.price_flags.extend(price_flags);
+    report.timestamp_issues.extend(timestamp_issues);
+    for (form, count) in coordinate_forms {
+        *report.coordinate_forms.entry(form).or_default() += count;
+    }
+    report.location_issues.extend(location_issues);
+    report.postcode_issues.extend(postcode_issues);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::TransformError;
+    use crate::report::ProcessedFeed;
+    use crate::{combine_feeds, process_feeds};
+
+    /// A feed with rejected stations, fuzzy and unknown brands, flagged prices,
+    /// bad timestamps and bad locations spread throughout
+    fn large_feed(stations: usize, offset: usize) -> String {
+        let brands = ["bp", "Esso", "Shel", "Corner Shop", "tesco"];
+        let stations: Vec<String> = (0..stations)
+            .map(|i| {
+                let site_id = (i * 7 + offset) % stations;
+                if i % 97 == 0 {
+                    return format!(r#"{{"site_id":"{site_id}","brand":null}}"#);
+                }
+                let e10 = if i % 89 == 0 { "12.5" } else { "138.9" };
+                let latitude = if i % 79 == 0 { "-0.1" } else { "51.5" };
+                let e10_lu = if i % 83 == 0 { "yesterday" } else { "27/11/2024 09:00:00" };
+                format!(
+                    r#"{{"site_id":"{site_id:05}","brand":"{}","address":"{i} High Street","postcode":"AB1 2CD","location":{{"latitude":{latitude},"longitude":-0.1}},"prices":{{"E10":{{"price":{e10},"lu":"{e10_lu}"}},"B7":145.9,"LPG":79.9}}}}"#,
+                    brands[i % brands.len()],
+                )
+            })
+            .collect();
+        format!(
+            r#"{{"last_updated":"27/11/2024 11:45:32","stations":[{}]}}"#,
+            stations.join(",")
+        )
+    }
+
+    #[test]
+    fn test_matches_sequential_transform() {
+        let feed = large_feed(MIN_STATIONS * 3, 0);
+        let stations: Vec<&RawValue> =
+            serde_json::from_str::<crate::station_struts::FuelStationData>(&feed)
+                .unwrap()
+                .stations;
+        let feed_lu = "2024-11-27T11:45:32Z".parse().unwrap();
+        let options = TransformOptions::default();
+
+        let mut sequential_report = FeedReport::default();
+        let sequential =
+            crate::transform_stations(&stations, feed_lu, &options, &mut sequential_report);
+        let mut parallel_report = FeedReport::default();
+        let parallel = transform_stations(&stations, feed_lu, &options, &mut parallel_report);
+
+        assert_eq!(parallel, sequential);
+        assert_eq!(parallel_report, sequential_report);
+        assert!(!parallel_report.rejections.is_empty());
+        assert!(!parallel_report.price_flags.is_empty());
+        assert!(!parallel_report.timestamp_issues.is_empty());
+        assert!(!parallel_report.location_issues.is_empty());
+    }
+
+    /// Transforms a feed like `process_feed`, but always with the sequential
+    /// [`transform_stations`](crate::transform_stations)
+    fn process_feed_sequentially(json_data: &str) -> Result<ProcessedFeed, TransformError> {
+        let options = TransformOptions::default();
+        let feed = crate::read_feed(json_data)?;
+        let (feed_lu, timestamp_format) =
+            crate::parse_feed_timestamp(&feed.last_updated, &options)?;
+        let mut report = FeedReport {
+            timestamp_format: Some(timestamp_format),
+            ..FeedReport::default()
+        };
+        let stations = crate::transform_stations(&feed.stations, feed_lu, &options, &mut report);
+        Ok(ProcessedFeed { stations, report })
+    }
+
+    #[test]
+    fn test_combined_feeds_match_sequential() {
+        let feeds: Vec<String> = (0..4).map(|i| large_feed(MIN_STATIONS, i * 13)).collect();
+        let combined = process_feeds(feeds.iter().map(String::as_str));
+        let sequential = combine_feeds(
+            feeds
+                .iter()
+                .map(|feed| process_feed_sequentially(feed))
+                .collect(),
+        );
+
+        assert!(!combined.has_failed_feeds());
+        assert_eq!(combined.stations, sequential.stations);
+        let reports = |feeds: &[Result<FeedReport, TransformError>]| {
+            feeds
+                .iter()
+                .map(|feed| feed.as_ref().ok().cloned())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(reports(&combined.feeds), reports(&sequential.feeds));
+        assert_eq!(combined.conflicts, sequential.conflicts);
+        assert!(combined
+            .stations
+            .windows(2)
+            .all(|pair| pair[0].site_id < pair[1].site_id));
+    }
+}
@@ -0,0 +1,215 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Serialize, Serializer};
+
+use crate::error::ValidationError;
+use crate::report::FeedReport;
+use crate::station_struts::StationPrices;
+
+/// A valid UK postcode, in canonical form: upper case, with a single space
+/// between the outward and inward codes
+///
+/// # Parts
+///
+/// For `"SW1A 1AA"`:
+///
+/// | Part     | Value      |
+/// |----------|------------|
+/// | area     | `SW`       |
+/// | district | `SW1A`     |
+/// | sector   | `SW1A 1`   |
+/// | unit     | `SW1A 1AA` |
+///
+/// # Examples
+///
+/// ```rust
+/// use refuel_radar_transform::postcode::Postcode;
+///
+/// let postcode: Postcode = " sw1a1aa ".parse().unwrap();
+/// assert_eq!(postcode.unit(), "SW1A 1AA");
+/// assert_eq!(postcode.area(), "SW");
+/// assert_eq!(postcode.sector(), "SW1A 1");
+/// assert!("SW1A 1CA".parse::<Postcode>().is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Postcode {
+    canonical: String,
+    area_len: usize,
+    outward_len: usize,
+}
+
+impl Postcode {
+    /// The one or two letters at the start, e.g. `SW`
+    pub fn area(&self) -> &str {
+        &self.canonical[..self.area_len]
+    }
+
+    /// The outward code, e.g. `SW1A`
+    pub fn district(&self) -> &str {
+        &self.canonical[..self.outward_len]
+    }
+
+    /// The district and the first character of the inward code, e.g. `SW1A 1`
+    pub fn sector(&self) -> &str {
+        &self.canonical[..self.outward_len + 2]
+    }
+
+    /// The whole postcode, e.g. `SW1A 1AA`
+    pub fn unit(&self) -> &str {
+        &self.canonical
+    }
+
+    /// The inward code, e.g. `1AA`
+    pub fn inward(&self) -> &str {
+        &self.canonical[self.outward_len + 1..]
+    }
+}
+
+/// Parses a postcode in any case and with any spacing, following the UK
+/// postcode grammar, including the letters each position may hold. `GIR 0AA`
+/// is accepted; BFPO and overseas territory postcodes are not
+impl FromStr for Postcode {
+    type Err = ValidationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let compact: String = s
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .map(|c| c.to_ascii_uppercase())
+            .collect();
+        let invalid = || ValidationError::InvalidPostcode(s.to_string());
+
+        if !compact.is_ascii() || !(5..=7).contains(&compact.len()) {
+            return Err(invalid());
+        }
+        let (outward, inward) = compact.split_at(compact.len() - 3);
+        if compact != "GIR0AA" && !(is_outward_code(outward) && is_inward_code(inward)) {
+            return Err(invalid());
+        }
+
+        Ok(Postcode {
+            canonical: format!("{outward} {inward}"),
+            area_len: outward.bytes().take_while(u8::is_ascii_alphabetic).count(),
+            outward_len: outward.len(),
+        })
+    }
+}
+
+/// Checks an outward code is one of `A9`, `A99`, `A9A`, `AA9`, `AA99` or `AA9A`
+fn is_outward_code(outward: &str) -> bool {
+    let first = |c: u8| c.is_ascii_uppercase() && !b"QVX".contains(&c);
+    let second = |c: u8| c.is_ascii_uppercase() && !b"IJZ".contains(&c);
+    let third = |c: u8| b"ABCDEFGHJKPSTUW".contains(&c);
+    let fourth = |c: u8| b"ABEHMNPRVWXY".contains(&c);
+    let digit = |c: u8| c.is_ascii_digit();
+
+    match *outward.as_bytes() {
+        [a, n] => first(a) && digit(n),
+        [a, b, c] => first(a) && (digit(b) && (digit(c) || third(c)) || second(b) && digit(c)),
+        [a, b, n, c] => first(a) && second(b) && digit(n) && (digit(c) || fourth(c)),
+        _ => false,
+    }
+}
+
+/// Checks an inward code is a digit followed by two letters, none of `CIKMOV`
+fn is_inward_code(inward: &str) -> bool {
+    let letter = |c: u8| c.is_ascii_uppercase() && !b"CIKMOV".contains(&c);
+    match *inward.as_bytes() {
+        [n, a, b] => n.is_ascii_digit() && letter(a) && letter(b),
+        _ => false,
+    }
+}
+
+impl fmt::Display for Postcode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.canonical)
+    }
+}
+
+impl Serialize for Postcode {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.canonical)
+    }
+}
+
+/// A station postcode that is not a valid UK postcode, left as published
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PostcodeIssue {
+    pub site_id: String,
+    pub postcode: String,
+}
+
+/// Rewrites a station's postcode in canonical form, or records it in
+/// `report.postcode_issues` if it is not a valid UK postcode
+pub(crate) fn normalise_postcode(station: &mut StationPrices, report: &mut FeedReport) {
+    match station.postcode.parse::<Postcode>() {
+        Ok(postcode) => station.postcode = postcode.canonical,
+        Err(_) => report.postcode_issues.push(PostcodeIssue {
+            site_id: station.site_id.clone(),
+            postcode: station.postcode.clone(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalises_spacing_and_case() {
+        for input in ["sw1a1aa", "SW1A  1AA", "SW1A 1AA ", " Sw1A 1aA"] {
+            let postcode: Postcode = input.parse().unwrap();
+            assert_eq!(postcode.to_string(), "SW1A 1AA");
+        }
+        assert_eq!(
+            serde_json::to_string(&"gir0aa".parse::<Postcode>().unwrap()).unwrap(),
+            r#""GIR 0AA""#
+        );
+    }
+
+    #[test]
+    fn test_parts() {
+        let cases = [
+            ("M1 1AE", "M", "M1", "M1 1"),
+            ("B33 8TH", "B", "B33", "B33 8"),
+            ("W1A 0AX", "W", "W1A", "W1A 0"),
+            ("CR2 6XH", "CR", "CR2", "CR2 6"),
+            ("DN55 1PT", "DN", "DN55", "DN55 1"),
+            ("EC1A 1BB", "EC", "EC1A", "EC1A 1"),
+        ];
+        for (input, area, district, sector) in cases {
+            let postcode: Postcode = input.parse().unwrap();
+            assert_eq!(
+                (postcode.area(), postcode.district(), postcode.sector()),
+                (area, district, sector)
+            );
+            assert_eq!(postcode.unit(), input);
+            assert_eq!(postcode.inward(), &input[input.len() - 3..]);
+        }
+    }
+
+    #[test]
+    fn test_rejects_invalid_postcodes() {
+        for input in [
+            "",
+            "SW1A",
+            "SW1A 1AAA",
+            "1SW 1AA",
+            "QA1 1AA",
+            "AZ1 1AA",
+            "A1I 1AA",
+            "AA1C 1AA",
+            "SW1A 1CA",
+            "SW1A AAA",
+            "SW1A 1A",
+            "ÄB1 1AA",
+        ] {
+            assert_eq!(
+                input.parse::<Postcode>(),
+                Err(ValidationError::InvalidPostcode(input.to_string())),
+                "{input}"
+            );
+        }
+    }
+}
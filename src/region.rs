@@ -0,0 +1,336 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::postcode::Postcode;
+
+/// A country of the United Kingdom
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Country {
+    England,
+    Scotland,
+    Wales,
+    #[serde(rename = "Northern Ireland")]
+    NorthernIreland,
+}
+
+/// An ONS statistical region: the nine regions of England, plus Scotland,
+/// Wales and Northern Ireland
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum OnsRegion {
+    #[serde(rename = "North East")]
+    NorthEast,
+    #[serde(rename = "North West")]
+    NorthWest,
+    #[serde(rename = "Yorkshire and The Humber")]
+    YorkshireAndTheHumber,
+    #[serde(rename = "East Midlands")]
+    EastMidlands,
+    #[serde(rename = "West Midlands")]
+    WestMidlands,
+    #[serde(rename = "East of England")]
+    EastOfEngland,
+    London,
+    #[serde(rename = "South East")]
+    SouthEast,
+    #[serde(rename = "South West")]
+    SouthWest,
+    Wales,
+    Scotland,
+    #[serde(rename = "Northern Ireland")]
+    NorthernIreland,
+}
+
+impl OnsRegion {
+    /// The country the region is in
+    pub fn country(self) -> Country {
+        match self {
+            OnsRegion::Wales => Country::Wales,
+            OnsRegion::Scotland => Country::Scotland,
+            OnsRegion::NorthernIreland => Country::NorthernIreland,
+            _ => Country::England,
+        }
+    }
+
+    /// The region's name as the ONS writes it, e.g. `"Yorkshire and The Humber"`
+    pub fn name(self) -> &'static str {
+        match self {
+            OnsRegion::NorthEast => "North East",
+            OnsRegion::NorthWest => "North West",
+            OnsRegion::YorkshireAndTheHumber => "Yorkshire and The Humber",
+            OnsRegion::EastMidlands => "East Midlands",
+            OnsRegion::WestMidlands => "West Midlands",
+            OnsRegion::EastOfEngland => "East of England",
+            OnsRegion::London => "London",
+            OnsRegion::SouthEast => "South East",
+            OnsRegion::SouthWest => "South West",
+            OnsRegion::Wales => "Wales",
+            OnsRegion::Scotland => "Scotland",
+            OnsRegion::NorthernIreland => "Northern Ireland",
+        }
+    }
+}
+
+impl fmt::Display for OnsRegion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Where a station is, by its postcode
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Region {
+    /// Postcode area, e.g. `"SW"`
+    pub area: String,
+    /// Postcode area name, e.g. `"London SW"`
+    pub area_name: String,
+    pub ons_region: OnsRegion,
+    pub country: Country,
+}
+
+use OnsRegion::*;
+
+/// Every UK postcode area, sorted by area, with its name and the region most
+/// of it lies in. Guernsey, Jersey and the Isle of Man are not in the UK and
+/// are left out
+const AREAS: [(&str, &str, OnsRegion); 121] = [
+    ("AB", "Aberdeen", Scotland),
+    ("AL", "St Albans", EastOfEngland),
+    ("B", "Birmingham", WestMidlands),
+    ("BA", "Bath", SouthWest),
+    ("BB", "Blackburn", NorthWest),
+    ("BD", "Bradford", YorkshireAndTheHumber),
+    ("BH", "Bournemouth", SouthWest),
+    ("BL", "Bolton", NorthWest),
+    ("BN", "Brighton", SouthEast),
+    ("BR", "Bromley", London),
+    ("BS", "Bristol", SouthWest),
+    ("BT", "Belfast", NorthernIreland),
+    ("CA", "Carlisle", NorthWest),
+    ("CB", "Cambridge", EastOfEngland),
+    ("CF", "Cardiff", Wales),
+    ("CH", "Chester", NorthWest),
+    ("CM", "Chelmsford", EastOfEngland),
+    ("CO", "Colchester", EastOfEngland),
+    ("CR", "Croydon", London),
+    ("CT", "Canterbury", SouthEast),
+    ("CV", "Coventry", WestMidlands),
+    ("CW", "Crewe", NorthWest),
+    ("DA", "Dartford", SouthEast),
+    ("DD", "Dundee", Scotland),
+    ("DE", "Derby", EastMidlands),
+    ("DG", "Dumfries", Scotland),
+    ("DH", "Durham", NorthEast),
+    ("DL", "Darlington", NorthEast),
+    ("DN", "Doncaster", YorkshireAndTheHumber),
+    ("DT", "Dorchester", SouthWest),
+    ("DY", "Dudley", WestMidlands),
+    ("E", "London E", London),
+    ("EC", "London EC", London),
+    ("EH", "Edinburgh", Scotland),
+    ("EN", "Enfield", London),
+    ("EX", "Exeter", SouthWest),
+    ("FK", "Falkirk", Scotland),
+    ("FY", "Blackpool", NorthWest),
+    ("G", "Glasgow", Scotland),
+    ("GL", "Gloucester", SouthWest),
+    ("GU", "Guildford", SouthEast),
+    ("HA", "Harrow", London),
+    ("HD", "Huddersfield", YorkshireAndTheHumber),
+    ("HG", "Harrogate", YorkshireAndTheHumber),
+    ("HP", "Hemel Hempstead", SouthEast),
+    ("HR", "Hereford", WestMidlands),
+    ("HS", "Outer Hebrides", Scotland),
+    ("HU", "Hull", YorkshireAndTheHumber),
+    ("HX", "Halifax", YorkshireAndTheHumber),
+    ("IG", "Ilford", London),
+    ("IP", "Ipswich", EastOfEngland),
+    ("IV", "Inverness", Scotland),
+    ("KA", "Kilmarnock", Scotland),
+    ("KT", "Kingston upon Thames", SouthEast),
+    ("KW", "Kirkwall", Scotland),
+    ("KY", "Kirkcaldy", Scotland),
+    ("L", "Liverpool", NorthWest),
+    ("LA", "Lancaster", NorthWest),
+    ("LD", "Llandrindod Wells", Wales),
+    ("LE", "Leicester", EastMidlands),
+    ("LL", "Llandudno", Wales),
+    ("LN", "Lincoln", EastMidlands),
+    ("LS", "Leeds", YorkshireAndTheHumber),
+    ("LU", "Luton", EastOfEngland),
+    ("M", "Manchester", NorthWest),
+    ("ME", "Rochester", SouthEast),
+    ("MK", "Milton Keynes", SouthEast),
+    ("ML", "Motherwell", Scotland),
+    ("N", "London N", London),
+    ("NE", "Newcastle upon Tyne", NorthEast),
+    ("NG", "Nottingham", EastMidlands),
+    ("NN", "Northampton", EastMidlands),
+    ("NP", "Newport", Wales),
+    ("NR", "Norwich", EastOfEngland),
+    ("NW", "London NW", London),
+    ("OL", "Oldham", NorthWest),
+    ("OX", "Oxford", SouthEast),
+    ("PA", "Paisley", Scotland),
+    ("PE", "Peterborough", EastOfEngland),
+    ("PH", "Perth", Scotland),
+    ("PL", "Plymouth", SouthWest),
+    ("PO", "Portsmouth", SouthEast),
+    ("PR", "Preston", NorthWest),
+    ("RG", "Reading", SouthEast),
+    ("RH", "Redhill", SouthEast),
+    ("RM", "Romford", London),
+    ("S", "Sheffield", YorkshireAndTheHumber),
+    ("SA", "Swansea", Wales),
+    ("SE", "London SE", London),
+    ("SG", "Stevenage", EastOfEngland),
+    ("SK", "Stockport", NorthWest),
+    ("SL", "Slough", SouthEast),
+    ("SM", "Sutton", London),
+    ("SN", "Swindon", SouthWest),
+    ("SO", "Southampton", SouthEast),
+    ("SP", "Salisbury", SouthWest),
+    ("SR", "Sunderland", NorthEast),
+    ("SS", "Southend-on-Sea", EastOfEngland),
+    ("ST", "Stoke-on-Trent", WestMidlands),
+    ("SW", "London SW", London),
+    ("SY", "Shrewsbury", WestMidlands),
+    ("TA", "Taunton", SouthWest),
+    ("TD", "Galashiels", Scotland),
+    ("TF", "Telford", WestMidlands),
+    ("TN", "Tonbridge", SouthEast),
+    ("TQ", "Torquay", SouthWest),
+    ("TR", "Truro", SouthWest),
+    ("TS", "Cleveland", NorthEast),
+    ("TW", "Twickenham", London),
+    ("UB", "Southall", London),
+    ("W", "London W", London),
+    ("WA", "Warrington", NorthWest),
+    ("WC", "London WC", London),
+    ("WD", "Watford", EastOfEngland),
+    ("WF", "Wakefield", YorkshireAndTheHumber),
+    ("WN", "Wigan", NorthWest),
+    ("WR", "Worcester", WestMidlands),
+    ("WS", "Walsall", WestMidlands),
+    ("WV", "Wolverhampton", WestMidlands),
+    ("YO", "York", YorkshireAndTheHumber),
+    ("ZE", "Lerwick", Scotland),
+];
+
+/// Postcode districts on the other side of a national border from the rest
+/// of their area
+const DISTRICT_OVERRIDES: [(&str, OnsRegion); 16] = [
+    // Berwick-upon-Tweed, in an otherwise Scottish area
+    ("TD15", NorthEast),
+    // Flintshire and Wrexham, in the Chester area
+    ("CH5", Wales),
+    ("CH6", Wales),
+    ("CH7", Wales),
+    ("CH8", Wales),
+    // Powys and Ceredigion, in the Shrewsbury area
+    ("SY15", Wales),
+    ("SY16", Wales),
+    ("SY17", Wales),
+    ("SY18", Wales),
+    ("SY19", Wales),
+    ("SY20", Wales),
+    ("SY21", Wales),
+    ("SY22", Wales),
+    ("SY23", Wales),
+    ("SY24", Wales),
+    ("SY25", Wales),
+];
+
+/// Classifies a postcode by its area, ONS region and country, using the
+/// crate's bundled table of postcode areas.
+///
+/// # Accuracy
+///
+/// Areas that straddle a national border are split by district where a
+/// whole district lies across the border (see `DISTRICT_OVERRIDES`). Districts
+/// that the border itself runs through, such as CH4 (Saltney and Broughton),
+/// HR3 (Hay-on-Wye), LD8 (Presteigne), NP16 (Chepstow), SY10 (Oswestry),
+/// SY14 (Malpas) and TD12 (Coldstream and Cornhill-on-Tweed), take the country
+/// of the rest of their area, so stations near the border may be given the
+/// wrong country. Areas that straddle a boundary between English regions are
+/// given the region most of the area lies in.
+///
+/// Returns `None` for postcodes outside the UK (Guernsey, Jersey and the Isle
+/// of Man).
+///
+/// # Examples
+///
+/// ```rust
+/// use refuel_radar_transform::postcode::Postcode;
+/// use refuel_radar_transform::region::{classify, Country, OnsRegion};
+///
+/// let postcode: Postcode = "CH7 1AA".parse().unwrap();
+/// let region = classify(&postcode).unwrap();
+/// assert_eq!((region.area.as_str(), region.area_name.as_str()), ("CH", "Chester"));
+/// assert_eq!((region.ons_region, region.country), (OnsRegion::Wales, Country::Wales));
+/// ```
+pub fn classify(postcode: &Postcode) -> Option<Region> {
+    let index = AREAS
+        .binary_search_by_key(&postcode.area(), |&(area, _, _)| area)
+        .ok()?;
+    let (area, area_name, ons_region) = AREAS[index];
+    let ons_region = DISTRICT_OVERRIDES
+        .iter()
+        .find(|(district, _)| *district == postcode.district())
+        .map_or(ons_region, |&(_, region)| region);
+
+    Some(Region {
+        area: area.to_string(),
+        area_name: area_name.to_string(),
+        ons_region,
+        country: ons_region.country(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn classify_str(postcode: &str) -> Option<Region> {
+        classify(&postcode.parse().unwrap())
+    }
+
+    #[test]
+    fn test_table_is_sorted() {
+        assert!(AREAS.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    }
+
+    #[test]
+    fn test_classify() {
+        let cases = [
+            ("SW1A 1AA", "London SW", London, Country::England),
+            ("M1 1AE", "Manchester", NorthWest, Country::England),
+            ("EH1 1YZ", "Edinburgh", Scotland, Country::Scotland),
+            (
+                "BT1 5GS",
+                "Belfast",
+                NorthernIreland,
+                Country::NorthernIreland,
+            ),
+            ("CF10 1EP", "Cardiff", Wales, Country::Wales),
+            ("TD15 1BA", "Galashiels", NorthEast, Country::England),
+            ("TD1 1AA", "Galashiels", Scotland, Country::Scotland),
+            ("SY23 1AA", "Shrewsbury", Wales, Country::Wales),
+            ("SY2 6AA", "Shrewsbury", WestMidlands, Country::England),
+            ("CH1 1AA", "Chester", NorthWest, Country::England),
+        ];
+        for (postcode, area_name, ons_region, country) in cases {
+            let region = classify_str(postcode).unwrap();
+            assert_eq!(
+                (region.area_name.as_str(), region.ons_region, region.country),
+                (area_name, ons_region, country),
+                "{postcode}"
+            );
+        }
+        assert_eq!(classify_str("JE2 3AB"), None);
+        assert_eq!(
+            serde_json::to_value(classify_str("YO1 7HH").unwrap()).unwrap()["ons_region"],
+            "Yorkshire and The Humber"
+        );
+    }
+}
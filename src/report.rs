@@ -0,0 +1,93 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::fuel::FuelType;
+use crate::location::{CoordinateForm, LocationIssue};
+use crate::postcode::PostcodeIssue;
+use crate::station_struts::StationPriceLastUpdated;
+use crate::units::{PriceFlag, PriceUnit};
+
+/// A station entry that was dropped from a feed, and why
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StationRejection {
+    /// Position of the entry in the feed's `stations` array
+    pub index: usize,
+    /// The entry's `site_id`, if it could be recovered
+    pub site_id: Option<String>,
+    /// The deserialization error that caused the rejection
+    pub reason: String,
+}
+
+/// A station or price timestamp that could not be parsed, so a less specific one was used
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TimestampIssue {
+    pub site_id: String,
+    /// The fuel the timestamp belonged to, or `None` for a station-level timestamp
+    pub fuel_type: Option<FuelType>,
+    pub value: String,
+    pub reason: String,
+}
+
+/// Feed quality information gathered while transforming a single feed
+///
+/// # Use Case
+///
+/// Serialized alongside the transformed stations so the number of stations
+/// each retailer feed loses (and why) can be tracked over time
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct FeedReport {
+    /// The timestamp format that matched the feed's `last_updated`
+    pub timestamp_format: Option<String>,
+    /// Number of stations that made it into the output
+    pub accepted: usize,
+    /// Every rejected station entry, in feed order
+    pub rejections: Vec<StationRejection>,
+    /// Number of rejections per distinct reason
+    pub rejection_counts: BTreeMap<String, usize>,
+    /// Brands that were not found in the brand table, with the number of stations using each
+    pub unmatched_brands: BTreeMap<String, usize>,
+    /// Raw brands that were only matched by fuzzy matching, with the canonical brand chosen
+    pub fuzzy_brand_matches: BTreeMap<String, String>,
+    /// Price keys that are not a known fuel type, with the number of stations using each
+    pub unrecognised_fuel_types: BTreeMap<String, usize>,
+    /// Fuel types given under more than one price key (e.g. "E10" and "e10"), with
+    /// the number of stations doing so; only one of the prices is kept
+    pub duplicate_fuel_types: BTreeMap<String, usize>,
+    /// Number of stations whose prices were detected in each unit
+    pub price_units: BTreeMap<PriceUnit, usize>,
+    /// Prices removed because they could not be confidently normalised to pence
+    pub price_flags: Vec<PriceFlag>,
+    /// Station and price timestamps that could not be parsed
+    pub timestamp_issues: Vec<TimestampIssue>,
+    /// Number of stations whose coordinates were written in each form
+    pub coordinate_forms: BTreeMap<CoordinateForm, usize>,
+    /// Station locations that were repaired, or that caused a rejection
+    pub location_issues: Vec<LocationIssue>,
+    /// Station postcodes that are not valid UK postcodes, left as published
+    pub postcode_issues: Vec<PostcodeIssue>,
+}
+
+impl FeedReport {
+    /// Records a rejected station entry and bumps the count for its reason
+    pub(crate) fn reject(&mut self, index: usize, site_id: Option<String>, reason: String) {
+        *self.rejection_counts.entry(reason.clone()).or_default() += 1;
+        self.rejections.push(StationRejection {
+            index,
+            site_id,
+            reason,
+        });
+    }
+
+    /// Returns `true` if any station entry was rejected
+    pub fn has_rejections(&self) -> bool {
+        !self.rejections.is_empty()
+    }
+}
+
+/// The result of transforming a feed: the accepted stations plus a report on the rest
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ProcessedFeed {
+    pub stations: Vec<StationPriceLastUpdated>,
+    pub report: FeedReport,
+}
@@ -1,55 +1,293 @@
 use std::collections::HashMap;
 
-use serde::{Deserialize, Deserializer, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::value::RawValue;
 use serde_json::Value;
 
+use crate::bng::GridReference;
+use crate::brand::BrandTable;
+use crate::error::ValidationError;
+use crate::fuel::FuelType;
+use crate::location::{parse_coordinate, Axis, CoordinateForm};
+use crate::region::Region;
+
 /// Represents the raw input data structure for fuel station information
+///
+/// Stations are kept as unparsed slices of the feed, so each one is
+/// deserialized exactly once, straight into [`StationPrices`].
 #[derive(Debug, Serialize, Deserialize)]
-pub struct FuelStationData {
+pub struct FuelStationData<'a> {
     pub(crate) last_updated: String,
-    pub(crate) stations: Vec<serde_json::Value>,
+    #[serde(borrow)]
+    pub(crate) stations: Vec<&'a RawValue>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(try_from = "RawLocation")]
 pub struct Location {
-    #[serde(deserialize_with = "deserialize_string_to_f64")]
     pub(crate) latitude: f64,
-    #[serde(deserialize_with = "deserialize_string_to_f64")]
     pub(crate) longitude: f64,
+    /// British National Grid reference, as published when the feed gave one.
+    /// Written out only when [`crate::TransformOptions::grid_references`] is set
+    #[serde(flatten)]
+    pub(crate) grid: Option<GridReference>,
+    /// How the coordinates were written in the feed (the less plain of the two)
+    #[serde(skip)]
+    pub(crate) form: CoordinateForm,
+}
+
+/// Locations are equal when their coordinates are; the form they were read
+/// in is ignored, so output read back compares equal to the original
+impl PartialEq for Location {
+    fn eq(&self, other: &Self) -> bool {
+        self.latitude == other.latitude && self.longitude == other.longitude
+    }
+}
+
+/// A location as published, before the coordinate forms are combined. Feeds
+/// give either WGS84 latitude and longitude or OSGB36 easting and northing
+#[derive(Deserialize)]
+struct RawLocation {
+    #[serde(default, deserialize_with = "deserialize_latitude")]
+    latitude: Option<(f64, CoordinateForm)>,
+    #[serde(default, deserialize_with = "deserialize_longitude")]
+    longitude: Option<(f64, CoordinateForm)>,
+    #[serde(default, deserialize_with = "deserialize_grid_value")]
+    easting: Option<(f64, CoordinateForm)>,
+    #[serde(default, deserialize_with = "deserialize_grid_value")]
+    northing: Option<(f64, CoordinateForm)>,
 }
 
-// Custom deserializer to handle latitude and longitude
-fn deserialize_string_to_f64<'de, D>(deserializer: D) -> Result<f64, D::Error>
+/// Latitude and longitude are used when both are given, and any grid reference
+/// alongside them is dropped unchecked; otherwise the grid reference is
+/// converted to WGS84
+impl TryFrom<RawLocation> for Location {
+    type Error = String;
+
+    fn try_from(raw: RawLocation) -> Result<Self, Self::Error> {
+        match raw {
+            RawLocation {
+                latitude: Some(latitude),
+                longitude: Some(longitude),
+                ..
+            } => Ok(Location {
+                latitude: latitude.0,
+                longitude: longitude.0,
+                grid: None,
+                form: latitude.1.max(longitude.1),
+            }),
+            RawLocation {
+                easting: Some(easting),
+                northing: Some(northing),
+                ..
+            } => {
+                let grid = GridReference {
+                    easting: easting.0,
+                    northing: northing.0,
+                };
+                // A fixed reason, so rejections group by it rather than by value
+                let location = Location::from_grid(grid)
+                    .map_err(|_| "grid reference is not on the British National Grid")?;
+                Ok(Location {
+                    form: easting.1.max(northing.1),
+                    ..location
+                })
+            }
+            RawLocation {
+                latitude: None,
+                easting: None,
+                ..
+            } => Err("missing field `latitude`".to_string()),
+            RawLocation {
+                latitude: Some(_), ..
+            } => Err("missing field `longitude`".to_string()),
+            RawLocation {
+                easting: Some(_), ..
+            } => Err("missing field `northing`".to_string()),
+        }
+    }
+}
+
+impl Location {
+    /// Creates a location from WGS84 decimal degrees.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`ValidationError`] if either coordinate is not finite, or
+    /// the latitude is outside -90 to 90 or the longitude outside -180 to 180.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use refuel_radar_transform::station_struts::Location;
+    ///
+    /// let location = Location::new(51.5, -0.1).unwrap();
+    /// assert_eq!(location.latitude(), 51.5);
+    /// assert!(Location::new(91.0, 0.0).is_err());
+    /// ```
+    pub fn new(latitude: f64, longitude: f64) -> Result<Self, ValidationError> {
+        if !latitude.is_finite() || !(-90.0..=90.0).contains(&latitude) {
+            return Err(ValidationError::InvalidLatitude(latitude));
+        }
+        if !longitude.is_finite() || !(-180.0..=180.0).contains(&longitude) {
+            return Err(ValidationError::InvalidLongitude(longitude));
+        }
+        Ok(Location {
+            latitude,
+            longitude,
+            grid: None,
+            form: CoordinateForm::Number,
+        })
+    }
+
+    /// Creates a location from a British National Grid reference, converting
+    /// it to WGS84; see [`GridReference`] for the accuracy of the conversion.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ValidationError::OffGrid`] if the easting or northing is not
+    /// finite or lies outside the grid.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use refuel_radar_transform::bng::GridReference;
+    /// use refuel_radar_transform::station_struts::Location;
+    ///
+    /// let grid = GridReference { easting: 530_268.0, northing: 179_640.0 };
+    /// let location = Location::from_grid(grid).unwrap();
+    /// assert!((location.latitude() - 51.5).abs() < 0.01);
+    /// assert_eq!(location.grid_reference(), grid);
+    /// ```
+    pub fn from_grid(grid: GridReference) -> Result<Self, ValidationError> {
+        if !grid.is_on_grid() {
+            return Err(ValidationError::OffGrid {
+                easting: grid.easting,
+                northing: grid.northing,
+            });
+        }
+        let (latitude, longitude) = grid.to_wgs84();
+        Ok(Location {
+            latitude,
+            longitude,
+            grid: Some(grid),
+            form: CoordinateForm::Number,
+        })
+    }
+
+    /// Latitude in decimal degrees
+    pub fn latitude(&self) -> f64 {
+        self.latitude
+    }
+
+    /// Longitude in decimal degrees
+    pub fn longitude(&self) -> f64 {
+        self.longitude
+    }
+
+    /// How the coordinates were written in the feed; see [`CoordinateForm`]
+    pub fn form(&self) -> CoordinateForm {
+        self.form
+    }
+
+    /// British National Grid reference: the one published in the feed, or
+    /// else converted from the latitude and longitude
+    pub fn grid_reference(&self) -> GridReference {
+        self.grid
+            .unwrap_or_else(|| GridReference::from_wgs84(self.latitude, self.longitude))
+    }
+}
+
+// Custom deserializer to handle latitude and longitude, in any form
+// `parse_coordinate` understands
+fn deserialize_coordinate<'de, D>(
+    deserializer: D,
+    axis: Axis,
+) -> Result<(f64, CoordinateForm), D::Error>
 where
     D: Deserializer<'de>,
 {
     let value: serde_json::Value = serde::Deserialize::deserialize(deserializer)?;
 
     match value {
-        serde_json::Value::String(s) => s
-            .parse::<f64>()
-            .map_err(|e| serde::de::Error::custom(format!("Invalid coordinate: {}", e))),
+        serde_json::Value::String(s) => {
+            parse_coordinate(&s, axis).ok_or_else(|| serde::de::Error::custom("Invalid coordinate"))
+        }
 
         serde_json::Value::Number(num) => num
             .as_f64()
+            .map(|value| (value, CoordinateForm::Number))
             .ok_or_else(|| serde::de::Error::custom("Invalid number")),
 
         _ => Err(serde::de::Error::custom("Invalid type for coordinate")),
     }
 }
 
-type PricesHashMap = HashMap<String, f64>;
+fn deserialize_latitude<'de, D>(deserializer: D) -> Result<Option<(f64, CoordinateForm)>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserialize_coordinate(deserializer, Axis::Latitude).map(Some)
+}
+
+fn deserialize_longitude<'de, D>(deserializer: D) -> Result<Option<(f64, CoordinateForm)>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserialize_coordinate(deserializer, Axis::Longitude).map(Some)
+}
+
+// Eastings and northings are whole metres or decimals, as numbers or numeric
+// strings. The coordinate forms are not tried: a comma here is more likely a
+// thousands separator than a decimal one
+fn deserialize_grid_value<'de, D>(
+    deserializer: D,
+) -> Result<Option<(f64, CoordinateForm)>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value: serde_json::Value = serde::Deserialize::deserialize(deserializer)?;
+
+    match value {
+        serde_json::Value::String(s) => s
+            .trim()
+            .parse()
+            .map(|value| Some((value, CoordinateForm::String)))
+            .map_err(|_| serde::de::Error::custom("Invalid grid coordinate")),
+
+        serde_json::Value::Number(num) => num
+            .as_f64()
+            .map(|value| Some((value, CoordinateForm::Number)))
+            .ok_or_else(|| serde::de::Error::custom("Invalid number")),
+
+        _ => Err(serde::de::Error::custom("Invalid type for grid coordinate")),
+    }
+}
+
+/// Prices in pence per litre, keyed by fuel type
+pub type PricesHashMap = HashMap<FuelType, f64>;
 
 /// Represents a price object with fuel price data and when that data was last updated
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct PriceLastUpdated {
-    #[serde(flatten)]
+    #[serde(flatten, serialize_with = "serialize_prices")]
     pub prices: PricesHashMap,
     // Last update (lu) date and time (ISO)
     // Shortened to lu to reduce file size
     pub lu: String,
 }
 
+/// Serializes prices in [`FuelType`] order, so output does not depend on
+/// `HashMap` iteration order
+pub(crate) fn serialize_prices<S>(prices: &PricesHashMap, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let mut sorted: Vec<(&FuelType, &f64)> = prices.iter().collect();
+    sorted.sort_by_key(|&(fuel_type, _)| fuel_type);
+    serializer.collect_map(sorted)
+}
+
 /// Represents a fuel station's price information with last updated timestamp.
 ///
 /// # Structure
@@ -64,20 +302,82 @@ pub struct PriceLastUpdated {
 ///
 /// - `Debug`: Enables convenient debugging and printing
 /// - `Serialize`: Allows conversion to various formats (JSON, etc.)
+/// - `Deserialize`: Reads previously produced output back losslessly, including
+///   output written before `raw_brand` was added, whose brand is used as the raw brand
 /// - `Clone`: Enables deep copying of the entire station data
 ///
 /// # Use Case
 ///
 /// Designed to store enriched station pricing data with timestamp information,
 /// useful for tracking historical pricing and data updates
-#[derive(Debug, Serialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(from = "StoredStation")]
 pub struct StationPriceLastUpdated {
     pub site_id: String,
     pub brand: String,
+    /// The brand exactly as it appeared in the feed, before normalisation
+    pub raw_brand: String,
     pub address: String,
     pub postcode: String,
     pub location: Location,
     pub prices: Vec<PriceLastUpdated>,
+    /// Where the station is by its postcode, when transformed with
+    /// [`crate::TransformOptions::regions`] and the postcode is in the UK
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub region: Option<Region>,
+}
+
+/// A station as written by any version of the transform
+#[derive(Deserialize)]
+struct StoredStation {
+    site_id: String,
+    brand: String,
+    #[serde(default)]
+    raw_brand: Option<String>,
+    address: String,
+    postcode: String,
+    location: Location,
+    prices: Vec<PriceLastUpdated>,
+    #[serde(default)]
+    region: Option<Region>,
+}
+
+impl From<StoredStation> for StationPriceLastUpdated {
+    fn from(stored: StoredStation) -> Self {
+        StationPriceLastUpdated {
+            raw_brand: stored.raw_brand.unwrap_or_else(|| stored.brand.clone()),
+            site_id: stored.site_id,
+            brand: stored.brand,
+            address: stored.address,
+            postcode: stored.postcode,
+            location: stored.location,
+            prices: stored.prices,
+            region: stored.region,
+        }
+    }
+}
+
+impl StationPriceLastUpdated {
+    /// The most recent price seen for each fuel type across the price history
+    ///
+    /// History entries are oldest first, so later entries override earlier ones.
+    pub fn latest_prices(&self) -> HashMap<FuelType, f64> {
+        let mut latest = HashMap::new();
+        for snapshot in &self.prices {
+            latest.extend(
+                snapshot
+                    .prices
+                    .iter()
+                    .map(|(fuel, &price)| (fuel.clone(), price)),
+            );
+        }
+        latest
+    }
+
+    /// The most recent `lu` in the price history, if there is any history
+    pub fn latest_lu(&self) -> Option<&str> {
+        self.prices.last().map(|snapshot| snapshot.lu.as_str())
+    }
 }
 
 /// Custom price deserialization function with robust parsing and filtering.
@@ -87,6 +387,8 @@ pub struct StationPriceLastUpdated {
 /// Transforms input data by:
 /// - Converting various input types to floating-point prices
 /// - Filtering out non-positive or invalid price values
+/// - Parsing price keys into canonical [`FuelType`]s, so "E10", "e10 " and
+///   "Unleaded" become the same fuel
 /// - Handling different serialization formats flexibly
 ///
 /// # Supported Input Types
@@ -94,6 +396,8 @@ pub struct StationPriceLastUpdated {
 /// Handles price inputs as:
 /// - Numeric values
 /// - String representations of numbers
+/// - Objects with a `price` (either of the above) and an optional
+///   `last_updated`, `updated_at` or `lu` timestamp for that price
 ///
 /// # Filtering Criteria
 ///
@@ -101,6 +405,11 @@ pub struct StationPriceLastUpdated {
 /// - Removes entries with:
 ///   * Non-numeric values
 ///   * Zero or negative prices
+/// - When several keys parse to the same fuel type, only one price is kept and
+///   the fuel type is recorded in `duplicates`. Keys are read in byte order, as
+///   `serde_json::Map` keeps them sorted, so the price under "E10" is kept over
+///   "e10" whichever the feed lists first
+/// - A key named "lu" is dropped, as it would clash with `PriceLastUpdated::lu`
 ///
 /// # Performance
 ///
@@ -113,25 +422,88 @@ pub struct StationPriceLastUpdated {
 /// ```rust
 /// // Hypothetical JSON input
 /// // {"unleaded": 1.50, "diesel": "1.75", "invalid": "not a number"}
-/// // Result: {"unleaded": 1.50, "diesel": 1.75}
+/// // Result: {E10: 1.50, B7: 1.75}
+///
+/// // {"E10": {"price": 138.9, "last_updated": "27/11/2024 09:00:00"}}
+/// // Result: {E10: 138.9}, updated {E10: "27/11/2024 09:00:00"}
 /// ```
-fn deserialize_prices<'de, D>(deserializer: D) -> Result<PricesHashMap, D::Error>
+fn deserialize_prices<'de, D>(deserializer: D) -> Result<RawPrices, D::Error>
 where
     D: Deserializer<'de>,
 {
-    let map: HashMap<String, Value> = HashMap::deserialize(deserializer)?;
-    Ok(map
-        .into_iter()
-        .filter_map(|(key, value)| {
-            match value {
-                Value::Number(num) => num.as_f64(),
-                Value::String(s) => s.parse::<f64>().ok(),
-                _ => None,
+    let map: serde_json::Map<String, Value> = serde_json::Map::deserialize(deserializer)?;
+    let mut raw = RawPrices {
+        prices: PricesHashMap::with_capacity(map.len()),
+        updated: HashMap::new(),
+        duplicates: Vec::new(),
+    };
+    for (key, value) in map {
+        // A price may be an object carrying its own update time
+        let (value, updated) = match value {
+            Value::Object(mut entry) => (
+                entry.remove("price").unwrap_or(Value::Null),
+                timestamp_field(&entry),
+            ),
+            value => (value, None),
+        };
+        let price = match value {
+            Value::Number(num) => num.as_f64(),
+            Value::String(s) => s.trim().parse::<f64>().ok(),
+            _ => None,
+        }
+        .filter(|&v| v > 0.0);
+
+        // "lu" is reserved for the timestamp that prices are flattened alongside
+        if key.trim().eq_ignore_ascii_case("lu") {
+            continue;
+        }
+        if let Some(price) = price {
+            let Ok(fuel_type) = key.parse::<FuelType>();
+            if raw.prices.contains_key(&fuel_type) {
+                if !raw.duplicates.contains(&fuel_type) {
+                    raw.duplicates.push(fuel_type);
+                }
+                continue;
             }
-            .filter(|&v| v > 0.0)
-            .map(|v| (key, v))
-        })
-        .collect())
+            raw.prices.insert(fuel_type.clone(), price);
+            if let Some(updated) = updated {
+                raw.updated.insert(fuel_type, updated);
+            }
+        }
+    }
+    Ok(raw)
+}
+
+/// Field names a station or price may carry its own update time under
+const TIMESTAMP_FIELDS: [&str; 3] = ["last_updated", "updated_at", "lu"];
+
+/// Returns the first string timestamp field of a JSON object
+fn timestamp_field(object: &serde_json::Map<String, Value>) -> Option<String> {
+    TIMESTAMP_FIELDS
+        .iter()
+        .find_map(|field| object.get(*field).and_then(Value::as_str))
+        .map(str::to_string)
+}
+
+// Reads a station's update time if it is a string, and ignores it otherwise,
+// so the station falls back to the feed's `last_updated` rather than being rejected
+fn deserialize_timestamp<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Value::deserialize(deserializer)? {
+        Value::String(s) => Ok(Some(s)),
+        _ => Ok(None),
+    }
+}
+
+/// Prices read from a station's `prices` object, with any per-price update times
+/// and the fuel types given under more than one key
+#[derive(Debug, Default)]
+struct RawPrices {
+    prices: PricesHashMap,
+    updated: HashMap<FuelType, String>,
+    duplicates: Vec<FuelType>,
 }
 
 /// Represents a fuel station's detailed information and pricing.
@@ -152,7 +524,9 @@ where
 /// # Visibility
 ///
 /// All fields are `pub(crate)`, meaning they're accessible within the current crate,
-/// providing a balance between encapsulation and internal flexibility
+/// providing a balance between encapsulation and internal flexibility. Other
+/// crates read them through the accessor methods and build stations with
+/// [`StationPrices::new`].
 #[derive(Serialize, Clone)]
 pub struct StationPrices {
     pub(crate) site_id: String,
@@ -161,6 +535,118 @@ pub struct StationPrices {
     pub(crate) postcode: String,
     pub(crate) location: Location,
     pub(crate) prices: PricesHashMap,
+    /// The brand exactly as it appeared in the feed
+    pub(crate) raw_brand: String,
+    /// The station's own update time, unparsed, if the feed provides one
+    pub(crate) last_updated: Option<String>,
+    /// Per-price update times, unparsed, for the prices the feed provides them for
+    pub(crate) price_updated: HashMap<FuelType, String>,
+    /// Fuel types the feed gave under more than one price key, of which one was kept
+    pub(crate) duplicate_prices: Vec<FuelType>,
+}
+
+impl StationPrices {
+    /// Creates a station with the given details and prices.
+    ///
+    /// The brand is formatted with the default brand table, keeping `brand`
+    /// as the raw brand. The station has no update times of its own, so the
+    /// transform treats its prices as updated at the feed's `last_updated`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`ValidationError`] if `site_id` is blank or any price is not
+    /// finite and positive.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::collections::HashMap;
+    /// use refuel_radar_transform::station_struts::{Location, StationPrices};
+    /// use refuel_radar_transform::FuelType;
+    ///
+    /// let station = StationPrices::new(
+    ///     "a",
+    ///     "bp",
+    ///     "1 High Street",
+    ///     "AB1 2CD",
+    ///     Location::new(51.5, -0.1).unwrap(),
+    ///     HashMap::from([(FuelType::E10, 138.9)]),
+    /// )
+    /// .unwrap();
+    /// assert_eq!(station.brand(), "BP");
+    /// assert_eq!(station.prices()[&FuelType::E10], 138.9);
+    /// ```
+    pub fn new(
+        site_id: impl Into<String>,
+        brand: impl Into<String>,
+        address: impl Into<String>,
+        postcode: impl Into<String>,
+        location: Location,
+        prices: PricesHashMap,
+    ) -> Result<Self, ValidationError> {
+        let site_id = site_id.into();
+        if site_id.trim().is_empty() {
+            return Err(ValidationError::EmptySiteId);
+        }
+        if let Some((fuel_type, &price)) = prices
+            .iter()
+            .find(|(_, &price)| !price.is_finite() || price <= 0.0)
+        {
+            return Err(ValidationError::InvalidPrice {
+                fuel_type: fuel_type.clone(),
+                price,
+            });
+        }
+
+        let raw_brand = brand.into();
+        Ok(StationPrices {
+            site_id,
+            brand: format_brand(raw_brand.clone()),
+            raw_brand,
+            address: address.into(),
+            postcode: postcode.into(),
+            location,
+            prices,
+            last_updated: None,
+            price_updated: HashMap::new(),
+            duplicate_prices: Vec::new(),
+        })
+    }
+
+    /// The station's unique site ID
+    pub fn site_id(&self) -> &str {
+        &self.site_id
+    }
+
+    /// The brand after formatting with the default brand table
+    pub fn brand(&self) -> &str {
+        &self.brand
+    }
+
+    /// The brand exactly as it appeared in the feed
+    pub fn raw_brand(&self) -> &str {
+        &self.raw_brand
+    }
+
+    /// The street address
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    /// The postcode, as published in the feed
+    pub fn postcode(&self) -> &str {
+        &self.postcode
+    }
+
+    /// Where the station is; see [`Location`]
+    pub fn location(&self) -> &Location {
+        &self.location
+    }
+
+    /// Prices by fuel type, as published (before unit normalisation)
+    pub fn prices(&self) -> &PricesHashMap {
+        &self.prices
+    }
 }
 
 /// Custom Debug implementation for more controlled logging and debugging.
@@ -175,10 +661,14 @@ impl std::fmt::Debug for StationPrices {
         f.debug_struct("StationPrices")
             .field("site_id", &self.site_id)
             .field("brand", &self.brand)
+            .field("raw_brand", &self.raw_brand)
             .field("address", &self.address)
             .field("postcode", &self.postcode)
             .field("location", &self.location)
             .field("prices", &self.prices)
+            .field("last_updated", &self.last_updated)
+            .field("price_updated", &self.price_updated)
+            .field("duplicate_prices", &self.duplicate_prices)
             .finish()
     }
 }
@@ -195,6 +685,8 @@ impl std::fmt::Debug for StationPrices {
 ///
 /// - Validates brand is not null
 /// - Applies brand name formatting during deserialization
+/// - Keeps station-level and price-level update times, if present, for the
+///   transform to parse
 /// - Provides robust error handling
 impl<'de> Deserialize<'de> for StationPrices {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
@@ -209,33 +701,37 @@ impl<'de> Deserialize<'de> for StationPrices {
             postcode: String,
             location: Location,
             #[serde(deserialize_with = "deserialize_prices")]
-            prices: PricesHashMap,
+            prices: RawPrices,
+            #[serde(default, deserialize_with = "deserialize_timestamp")]
+            last_updated: Option<String>,
+            #[serde(default, deserialize_with = "deserialize_timestamp")]
+            updated_at: Option<String>,
+            #[serde(default, deserialize_with = "deserialize_timestamp")]
+            lu: Option<String>,
         }
 
         let temp = TempStationPrices::deserialize(deserializer)?;
-        if temp.brand.is_none() {
-            Err(serde::de::Error::custom("brand is null"))
-        } else {
-            let brand_name = format_brand(temp.brand.unwrap());
-            Ok(StationPrices {
-                site_id: temp.site_id,
-                brand: brand_name,
-                address: temp.address,
-                postcode: temp.postcode,
-                location: temp.location,
-                prices: temp.prices,
-            })
-        }
+        let Some(brand) = temp.brand else {
+            return Err(serde::de::Error::custom("brand is null"));
+        };
+
+        Ok(StationPrices {
+            site_id: temp.site_id,
+            brand: format_brand(brand.clone()),
+            raw_brand: brand,
+            address: temp.address,
+            postcode: temp.postcode,
+            location: temp.location,
+            prices: temp.prices.prices,
+            // The first of the `TIMESTAMP_FIELDS` that is a string, as for prices
+            last_updated: temp.last_updated.or(temp.updated_at).or(temp.lu),
+            price_updated: temp.prices.updated,
+            duplicate_prices: temp.prices.duplicates,
+        })
     }
 }
 
-/// Standardizes and formats brand names to a consistent representation.
-///
-/// This function performs brand name normalization by:
-/// - Trimming whitespace
-/// - Converting to lowercase for matching
-/// - Applying predefined formatting rules
-/// - Preserving original casing for known brands
+/// Standardizes and formats brand names using the default [`BrandTable`].
 ///
 /// # Parameters
 ///
@@ -243,56 +739,126 @@ impl<'de> Deserialize<'de> for StationPrices {
 ///
 /// # Returns
 ///
-/// A `String` with the standardized brand name
-///
-/// # Formatting Rules
-///
-/// - Removes leading and trailing whitespace
-/// - Converts input to lowercase for consistent matching
-/// - Maps specific brand names to their preferred representation
-/// - Maintains original input for unrecognized brands
+/// The canonical brand name, or the trimmed input for unrecognized brands
 ///
 /// # Examples
 ///
-/// ```rust
+/// ```rust,ignore
 /// assert_eq!(format_brand("bp".to_string()), "BP");
 /// assert_eq!(format_brand("  Sainsbury's  ".to_string()), "Sainsbury's");
 /// assert_eq!(format_brand("unknown brand".to_string()), "unknown brand");
 /// ```
-///
-/// # Brand Mapping
-///
-/// Supports consistent formatting for various fuel station brands:
-/// - "applegreen" → "Applegreen"
-/// - "bp" → "BP"
-/// - "esso" → "Esso"
-/// - ... and many more predefined mappings
-///
-/// # Performance
-///
-/// - O(1) time complexity for brand matching
-/// - Minimal overhead for string processing
 fn format_brand(brand: String) -> String {
-    let input_brand = brand.trim().to_lowercase();
-    let output_brand = match input_brand.as_str() {
-        "applegreen" => "Applegreen",
-        "asda express" => "ASDA Express",
-        "asda" => "ASDA",
-        "bp" => "BP",
-        "coop" => "Co Op",
-        "essar" => "Essar",
-        "esso" => "Esso",
-        "gulf" => "Gulf",
-        "harvest energy" => "Harvest Engery",
-        "jet" => "JET",
-        "morrisons" => "Morrisons",
-        "murco" => "Murco",
-        "sainsbury's" => "Sainsbury's",
-        "shell" => "Shell",
-        "tesco" => "Tesco",
-        "texaco" => "Texaco",
-        _ => brand.as_str(),
-    };
+    BrandTable::shared_default().format(&brand)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::try_process_data;
+
+    const FEED: &str = r#"{
+        "last_updated": "27/07/2024 12:00:00",
+        "stations": [{
+            "site_id": "a",
+            "brand": "Co-op",
+            "address": "1 High Street",
+            "postcode": "AB1 2CD",
+            "location": { "latitude": "51.512345678901", "longitude": -0.1 },
+            "prices": {
+                "E10": { "price": 131.9, "last_updated": "27/07/2024 08:00:00" },
+                "B7": "139.9",
+                "LPG": 89.9,
+                "lu": 100
+            }
+        }, {
+            "site_id": "b",
+            "brand": "Esso",
+            "address": "2 High Street",
+            "postcode": "AB1 2CE",
+            "location": { "latitude": 53.0, "longitude": -2.0 },
+            "prices": {}
+        }]
+    }"#;
+
+    #[test]
+    fn test_output_round_trip() {
+        let stations = try_process_data(FEED).unwrap();
+        assert_eq!(stations[0].prices.len(), 2);
+        assert_eq!(stations[0].prices[1].prices.len(), 2);
+
+        let output = serde_json::to_string(&stations).unwrap();
+        let read_back: Vec<StationPriceLastUpdated> = serde_json::from_str(&output).unwrap();
+        assert_eq!(read_back, stations);
+        assert_eq!(serde_json::to_string(&read_back).unwrap(), output);
+    }
 
-    output_brand.to_string()
+    #[test]
+    fn test_reads_output_without_raw_brand() {
+        // As written before `raw_brand` was added
+        let output = r#"[{
+            "site_id": "a",
+            "brand": "Co-op",
+            "address": "1 High Street",
+            "postcode": "AB1 2CD",
+            "location": { "latitude": 51.5, "longitude": -0.1 },
+            "prices": [{ "E10": 131.9, "lu": "2024-07-27T07:00:00+00:00" }]
+        }]"#;
+        let stations: Vec<StationPriceLastUpdated> = serde_json::from_str(output).unwrap();
+        assert_eq!(stations[0].raw_brand, "Co-op");
+        assert_eq!(stations[0].prices[0].prices[&FuelType::E10], 131.9);
+
+        let written = serde_json::to_string(&stations).unwrap();
+        let read_back: Vec<StationPriceLastUpdated> = serde_json::from_str(&written).unwrap();
+        assert_eq!(read_back, stations);
+    }
+
+    #[test]
+    fn test_pretty_output_round_trip() {
+        let stations = try_process_data(FEED).unwrap();
+        let output = serde_json::to_string_pretty(&stations).unwrap();
+        let read_back: Vec<StationPriceLastUpdated> = serde_json::from_str(&output).unwrap();
+        assert_eq!(read_back, stations);
+    }
+
+    #[test]
+    fn test_validating_constructors() {
+        assert!(matches!(
+            Location::new(f64::NAN, 0.0),
+            Err(ValidationError::InvalidLatitude(_))
+        ));
+        assert_eq!(
+            Location::new(51.5, 180.5),
+            Err(ValidationError::InvalidLongitude(180.5))
+        );
+
+        let location = Location::new(51.5, -0.1).unwrap();
+        let prices = PricesHashMap::from([(FuelType::B7, 0.0)]);
+        assert_eq!(
+            StationPrices::new(" ", "bp", "", "", location.clone(), PricesHashMap::new())
+                .unwrap_err(),
+            ValidationError::EmptySiteId
+        );
+        assert_eq!(
+            StationPrices::new("a", "bp", "", "", location.clone(), prices).unwrap_err(),
+            ValidationError::InvalidPrice {
+                fuel_type: FuelType::B7,
+                price: 0.0
+            }
+        );
+
+        let station = StationPrices::new(
+            "a",
+            "bp",
+            "1 High Street",
+            "AB1 2CD",
+            location,
+            PricesHashMap::from([(FuelType::E10, 138.9)]),
+        )
+        .unwrap();
+        assert_eq!(station.site_id(), "a");
+        assert_eq!((station.brand(), station.raw_brand()), ("BP", "bp"));
+        assert_eq!(station.location().longitude(), -0.1);
+        assert_eq!(station.prices()[&FuelType::E10], 138.9);
+    }
 }
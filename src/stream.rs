@@ -0,0 +1,644 @@
+use std::collections::VecDeque;
+use std::io::{self, BufRead, BufReader, Read, Write};
+
+use chrono::{DateTime, Utc};
+use serde::de::Error as _;
+use serde::Serialize;
+
+use crate::error::{json_type_name, TransformError};
+use crate::options::TransformOptions;
+use crate::report::FeedReport;
+use crate::station_struts::{StationPriceLastUpdated, StationPrices};
+use crate::units::normalise_price_units;
+use crate::{
+    check_station, finish_station, normalise_station, parse_feed_timestamp, reject_station,
+};
+
+/// Transforms a feed as it is read, yielding one station at a time.
+///
+/// See [`stream_feed_with`].
+pub fn stream_feed<R: Read>(reader: R) -> StationStream<R> {
+    stream_feed_with(reader, TransformOptions::default())
+}
+
+/// Transforms a feed as it is read, using the given [`TransformOptions`].
+///
+/// Only one station element is held in memory at a time, so feeds of any size
+/// can be transformed. Stations are yielded in feed order and rejected
+/// stations are recorded in [`StationStream::report`], as with
+/// [`process_feed_with`](crate::process_feed_with).
+///
+/// # Differences from `process_feed_with`
+///
+/// - Price units are always detected per station, as
+///   [`UnitDetection::PerFeed`](crate::units::UnitDetection::PerFeed) needs
+///   every price in the feed up front
+/// - If `stations` comes before `last_updated` in the feed, the station
+///   elements are buffered until `last_updated` is read
+///
+/// # Errors
+///
+/// A feed-level problem is yielded as an `Err` item, after which the stream
+/// ends. Stations yielded before it have already been transformed, so a
+/// feed that fails part way through produces partial output.
+///
+/// # Examples
+///
+/// ```rust
+/// use refuel_radar_transform::stream::stream_feed;
+///
+/// let json = r#"{"last_updated": "27/11/2024 11:45:32", "stations": [{
+///     "site_id": "a", "brand": "bp", "address": "1 High Street", "postcode": "AB1 2CD",
+///     "location": {"latitude": 51.5, "longitude": -0.1}, "prices": {"E10": 138.9}
+/// }]}"#;
+/// let mut stream = stream_feed(json.as_bytes());
+/// let station = stream.next().unwrap().unwrap();
+/// assert_eq!(station.brand, "BP");
+/// assert!(stream.next().is_none());
+/// assert_eq!(stream.report().accepted, 1);
+/// ```
+pub fn stream_feed_with<R: Read>(reader: R, options: TransformOptions) -> StationStream<R> {
+    StationStream {
+        scanner: Scanner::new(BufReader::new(reader)),
+        options,
+        report: FeedReport::default(),
+        state: State::Start,
+        last_updated: None,
+        feed_lu: None,
+        seen_stations: false,
+        next_index: 0,
+        pending: VecDeque::new(),
+    }
+}
+
+/// An iterator over the transformed stations of a feed; see [`stream_feed_with`]
+pub struct StationStream<R> {
+    scanner: Scanner<BufReader<R>>,
+    options: TransformOptions,
+    report: FeedReport,
+    state: State,
+    /// The feed's `last_updated`, once read
+    last_updated: Option<String>,
+    /// `last_updated` parsed, once there is a station that needs it
+    feed_lu: Option<DateTime<Utc>>,
+    seen_stations: bool,
+    next_index: usize,
+    /// Station elements read before `last_updated`
+    pending: VecDeque<Element>,
+}
+
+/// Where the stream is in the feed document
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Start,
+    Members { first: bool },
+    Stations { first: bool },
+    Done,
+}
+
+/// An unparsed station element and where it starts in the feed
+struct Element {
+    index: usize,
+    json: Vec<u8>,
+    start: Position,
+}
+
+impl<R: Read> StationStream<R> {
+    /// The report for the stations read so far
+    pub fn report(&self) -> &FeedReport {
+        &self.report
+    }
+
+    /// Consumes the stream, returning the report for the stations read
+    pub fn into_report(self) -> FeedReport {
+        self.report
+    }
+
+    fn step(&mut self) -> Result<Option<StationPriceLastUpdated>, TransformError> {
+        loop {
+            if self.last_updated.is_some() {
+                if let Some(element) = self.pending.pop_front() {
+                    match self.transform(element)? {
+                        Some(station) => return Ok(Some(station)),
+                        None => continue,
+                    }
+                }
+            }
+
+            match self.state {
+                State::Start => {
+                    self.scanner.skip_whitespace()?;
+                    match self.scanner.peek()? {
+                        Some(b'{') => self.scanner.bump()?,
+                        Some(byte) => {
+                            return Err(TransformError::UnexpectedShape {
+                                path: "$".to_string(),
+                                expected: "object",
+                                found: json_type_name(&[byte]),
+                            })
+                        }
+                        None => return Err(self.scanner.error("EOF while parsing a value")),
+                    };
+                    self.state = State::Members { first: true };
+                }
+                State::Members { first } => {
+                    self.scanner.skip_whitespace()?;
+                    if self.scanner.peek()? == Some(b'}') {
+                        self.scanner.bump()?;
+                        self.finish_document()?;
+                        continue;
+                    }
+                    if !first {
+                        self.scanner.expect(b',')?;
+                        self.scanner.skip_whitespace()?;
+                    }
+                    let key = self.read_key()?;
+                    self.state = State::Members { first: false };
+                    self.read_member(&key)?;
+                }
+                State::Stations { first } => {
+                    self.scanner.skip_whitespace()?;
+                    if self.scanner.peek()? == Some(b']') {
+                        self.scanner.bump()?;
+                        self.state = State::Members { first: false };
+                        continue;
+                    }
+                    if !first {
+                        self.scanner.expect(b',')?;
+                    }
+                    self.state = State::Stations { first: false };
+
+                    let mut json = Vec::new();
+                    let start = self.scanner.capture_value(&mut json)?;
+                    let element = Element {
+                        index: self.next_index,
+                        json,
+                        start,
+                    };
+                    self.next_index += 1;
+                    if self.last_updated.is_none() {
+                        self.pending.push_back(element);
+                    } else if let Some(station) = self.transform(element)? {
+                        return Ok(Some(station));
+                    }
+                }
+                State::Done => return Ok(None),
+            }
+        }
+    }
+
+    fn read_key(&mut self) -> Result<String, TransformError> {
+        if self.scanner.peek()? != Some(b'"') {
+            return Err(self.scanner.error("key must be a string"));
+        }
+        let mut json = Vec::new();
+        let start = self.scanner.capture_value(&mut json)?;
+        let key = serde_json::from_slice(&json).map_err(|e| fragment_error(&json, start, e))?;
+        self.scanner.skip_whitespace()?;
+        self.scanner.expect(b':')?;
+        Ok(key)
+    }
+
+    fn read_member(&mut self, key: &str) -> Result<(), TransformError> {
+        match key {
+            "last_updated" => {
+                let mut json = Vec::new();
+                let start = self.scanner.capture_value(&mut json)?;
+                let last_updated = serde_json::from_slice(&json).map_err(|e| {
+                    if e.is_data() {
+                        TransformError::UnexpectedShape {
+                            path: "last_updated".to_string(),
+                            expected: "string",
+                            found: json_type_name(&json),
+                        }
+                    } else {
+                        fragment_error(&json, start, e)
+                    }
+                })?;
+                self.last_updated = Some(last_updated);
+            }
+            "stations" => {
+                self.scanner.skip_whitespace()?;
+                if self.scanner.peek()? != Some(b'[') {
+                    let mut json = Vec::new();
+                    self.scanner.capture_value(&mut json)?;
+                    return Err(TransformError::UnexpectedShape {
+                        path: "stations".to_string(),
+                        expected: "array",
+                        found: json_type_name(&json),
+                    });
+                }
+                self.scanner.bump()?;
+                self.seen_stations = true;
+                self.state = State::Stations { first: true };
+            }
+            _ => {
+                self.scanner.capture_value(&mut Vec::new())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks the document once its closing brace has been read
+    fn finish_document(&mut self) -> Result<(), TransformError> {
+        self.scanner.skip_whitespace()?;
+        if self.scanner.peek()?.is_some() {
+            return Err(self.scanner.error("trailing characters"));
+        }
+        if self.last_updated.is_none() {
+            return Err(TransformError::MissingLastUpdated);
+        }
+        if !self.seen_stations {
+            return Err(TransformError::UnexpectedShape {
+                path: "stations".to_string(),
+                expected: "array",
+                found: "nothing",
+            });
+        }
+        self.state = State::Done;
+        Ok(())
+    }
+
+    /// Deserializes and transforms one station element, or records its rejection
+    fn transform(
+        &mut self,
+        element: Element,
+    ) -> Result<Option<StationPriceLastUpdated>, TransformError> {
+        let feed_lu = match self.feed_lu {
+            Some(feed_lu) => feed_lu,
+            None => {
+                let last_updated = self.last_updated.as_deref().unwrap_or_default();
+                let (feed_lu, format) = parse_feed_timestamp(last_updated, &self.options)?;
+                self.report.timestamp_format = Some(format);
+                *self.feed_lu.insert(feed_lu)
+            }
+        };
+
+        let station: StationPrices = match serde_json::from_slice(&element.json) {
+            Ok(station) => station,
+            Err(e) if e.is_data() => {
+                reject_station(&element.json, element.index, &e, &mut self.report);
+                return Ok(None);
+            }
+            Err(e) => return Err(fragment_error(&element.json, element.start, e)),
+        };
+        let Some(mut station) =
+            check_station(station, element.index, &self.options, &mut self.report)
+        else {
+            return Ok(None);
+        };
+        self.report.accepted += 1;
+
+        normalise_station(&mut station, &self.options, &mut self.report);
+        if let Some(price_units) = &self.options.price_units {
+            normalise_price_units(
+                std::slice::from_mut(&mut station),
+                price_units,
+                &mut self.report,
+            );
+        }
+        Ok(Some(finish_station(
+            station,
+            feed_lu,
+            &self.options,
+            &mut self.report,
+        )))
+    }
+}
+
+impl<R: Read> Iterator for StationStream<R> {
+    type Item = Result<StationPriceLastUpdated, TransformError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let result = self.step();
+        if result.is_err() {
+            self.state = State::Done;
+            self.pending.clear();
+        }
+        result.transpose()
+    }
+}
+
+/// A line, column and byte offset in the feed, as used by `InvalidJson`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Position {
+    line: usize,
+    column: usize,
+    offset: usize,
+}
+
+/// Reads the feed a byte at a time, tracking the position of the next byte
+struct Scanner<R> {
+    reader: R,
+    position: Position,
+}
+
+impl<R: BufRead> Scanner<R> {
+    fn new(reader: R) -> Self {
+        Scanner {
+            reader,
+            position: Position {
+                line: 1,
+                column: 1,
+                offset: 0,
+            },
+        }
+    }
+
+    fn peek(&mut self) -> io::Result<Option<u8>> {
+        Ok(self.reader.fill_buf()?.first().copied())
+    }
+
+    fn bump(&mut self) -> io::Result<Option<u8>> {
+        let byte = self.peek()?;
+        if let Some(byte) = byte {
+            self.reader.consume(1);
+            self.position.offset += 1;
+            if byte == b'\n' {
+                self.position.line += 1;
+                self.position.column = 1;
+            } else {
+                self.position.column += 1;
+            }
+        }
+        Ok(byte)
+    }
+
+    fn skip_whitespace(&mut self) -> io::Result<()> {
+        while let Some(b' ' | b'\n' | b'\r' | b'\t') = self.peek()? {
+            self.bump()?;
+        }
+        Ok(())
+    }
+
+    fn expect(&mut self, expected: u8) -> Result<(), TransformError> {
+        match self.peek()? {
+            Some(byte) if byte == expected => {
+                self.bump()?;
+                Ok(())
+            }
+            Some(_) => Err(self.error(&format!("expected `{}`", expected as char))),
+            None => Err(self.error("EOF while parsing an object")),
+        }
+    }
+
+    /// Copies the next JSON value into `out`, returning where it starts.
+    ///
+    /// Only the value's extent is found here: brackets are balanced and
+    /// strings skipped, but the value itself is checked when it is parsed.
+    fn capture_value(&mut self, out: &mut Vec<u8>) -> Result<Position, TransformError> {
+        self.skip_whitespace()?;
+        let start = self.position;
+        let mut depth = 0usize;
+        let mut in_string = false;
+        let mut escaped = false;
+
+        loop {
+            let Some(byte) = self.peek()? else {
+                return Err(self.error("EOF while parsing a value"));
+            };
+            if depth == 0 && !in_string && !out.is_empty() {
+                let scalar_ended = !matches!(out[0], b'"' | b'{' | b'[')
+                    && matches!(byte, b',' | b'}' | b']' | b' ' | b'\n' | b'\r' | b'\t');
+                if scalar_ended {
+                    return Ok(start);
+                }
+            }
+            self.bump()?;
+            out.push(byte);
+
+            if in_string {
+                match byte {
+                    _ if escaped => escaped = false,
+                    b'\\' => escaped = true,
+                    b'"' => in_string = false,
+                    _ => {}
+                }
+            } else {
+                match byte {
+                    b'"' => in_string = true,
+                    b'{' | b'[' => depth += 1,
+                    b'}' | b']' => depth = depth.saturating_sub(1),
+                    _ => {}
+                }
+            }
+
+            let container_ended = matches!(out[0], b'{' | b'[') && depth == 0;
+            let string_ended = out[0] == b'"' && out.len() > 1 && !in_string;
+            if container_ended || string_ended {
+                return Ok(start);
+            }
+        }
+    }
+
+    fn error(&self, message: &str) -> TransformError {
+        TransformError::InvalidJson {
+            line: self.position.line,
+            column: self.position.column,
+            offset: self.position.offset,
+            source: serde_json::Error::custom(message),
+        }
+    }
+}
+
+/// Turns a syntax error in a captured value into one positioned in the feed
+fn fragment_error(json: &[u8], start: Position, source: serde_json::Error) -> TransformError {
+    let line = source.line();
+    let column = source.column();
+    let offset = json
+        .split_inclusive(|&byte| byte == b'\n')
+        .take(line.saturating_sub(1))
+        .map(<[u8]>::len)
+        .sum::<usize>()
+        + column.saturating_sub(1);
+
+    TransformError::InvalidJson {
+        line: start.line + line.saturating_sub(1),
+        column: if line <= 1 {
+            start.column + column.saturating_sub(1)
+        } else {
+            column
+        },
+        offset: start.offset + offset.min(json.len()),
+        source,
+    }
+}
+
+/// Writes a JSON array to `writer` one element at a time.
+///
+/// The output is identical to serializing a `Vec` of the same elements with
+/// `serde_json::to_string` (or `to_string_pretty` when `pretty`), without
+/// the elements ever being held in memory together.
+///
+/// # Examples
+///
+/// ```rust
+/// use refuel_radar_transform::stream::JsonArrayWriter;
+///
+/// let mut writer = JsonArrayWriter::new(Vec::new(), false);
+/// writer.write(&1).unwrap();
+/// writer.write(&2).unwrap();
+/// assert_eq!(writer.finish().unwrap(), b"[1,2]");
+/// ```
+pub struct JsonArrayWriter<W: Write> {
+    writer: W,
+    pretty: bool,
+    len: usize,
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> JsonArrayWriter<W> {
+    pub fn new(writer: W, pretty: bool) -> Self {
+        JsonArrayWriter {
+            writer,
+            pretty,
+            len: 0,
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Writes the next element of the array
+    pub fn write<T: Serialize>(&mut self, element: &T) -> io::Result<()> {
+        let separator: &[u8] = match (self.len, self.pretty) {
+            (0, false) => b"[",
+            (0, true) => b"[\n",
+            (_, false) => b",",
+            (_, true) => b",\n",
+        };
+        self.writer.write_all(separator)?;
+
+        if self.pretty {
+            self.buffer.clear();
+            serde_json::to_writer_pretty(&mut self.buffer, element)?;
+            // Strings never contain a raw line break, so every line is JSON structure
+            for (i, line) in self.buffer.split(|&byte| byte == b'\n').enumerate() {
+                if i > 0 {
+                    self.writer.write_all(b"\n")?;
+                }
+                self.writer.write_all(b"  ")?;
+                self.writer.write_all(line)?;
+            }
+        } else {
+            serde_json::to_writer(&mut self.writer, element)?;
+        }
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Closes the array, returning the underlying writer
+    pub fn finish(mut self) -> io::Result<W> {
+        let end: &[u8] = match (self.len, self.pretty) {
+            (0, _) => b"[]",
+            (_, false) => b"]",
+            (_, true) => b"\n]",
+        };
+        self.writer.write_all(end)?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process_feed;
+
+    const FEED: &str = r#"{
+        "stations": [{
+            "site_id": "a",
+            "brand": "bp",
+            "address": "1 High Street",
+            "postcode": "AB1 2CD",
+            "location": { "latitude": 51.5, "longitude": -0.1 },
+            "prices": { "E10": "138.9", "B7": { "price": 1.459, "lu": "27/11/2024 09:00:00" } }
+        }, {
+            "site_id": "b",
+            "brand": null
+        }, {
+            "site_id": "c",
+            "brand": "Esso [Closed]",
+            "address": "2 High Street",
+            "postcode": "AB1 2CE",
+            "location": { "latitude": "51.4", "longitude": "-0.2" },
+            "prices": {}
+        }],
+        "source": { "name": "test", "tags": ["a]", "}"] },
+        "last_updated": "27/11/2024 11:45:32"
+    }"#;
+
+    #[test]
+    fn test_stream_matches_process_feed() {
+        let expected = process_feed(FEED).unwrap();
+
+        let mut stream = stream_feed(FEED.as_bytes());
+        let stations: Vec<_> = stream.by_ref().collect::<Result<_, _>>().unwrap();
+        assert_eq!(stations, expected.stations);
+        assert_eq!(stream.into_report(), expected.report);
+    }
+
+    #[test]
+    fn test_stream_errors() {
+        let errors = [
+            ("[]", "unexpected shape at `$`"),
+            (r#"{"stations": []}"#, "missing `last_updated`"),
+            (
+                r#"{"last_updated": 1, "stations": []}"#,
+                "unexpected shape at `last_updated`",
+            ),
+            (
+                r#"{"last_updated": "x", "stations": {}}"#,
+                "unexpected shape at `stations`",
+            ),
+            (r#"{"last_updated": "x"}"#, "found nothing"),
+            (
+                r#"{"last_updated": "x", "stations": []} x"#,
+                "trailing characters",
+            ),
+        ];
+        for (json, message) in errors {
+            let mut stream = stream_feed(json.as_bytes());
+            let error = stream.next().unwrap().unwrap_err();
+            assert!(error.to_string().contains(message), "{json}: {error}");
+            assert!(stream.next().is_none());
+        }
+
+        let json = "{\"last_updated\": \"27/11/2024 11:45:32\",\n \"stations\": [{\"site_id\": }]}";
+        let mut stream = stream_feed(json.as_bytes());
+        match stream.next() {
+            Some(Err(TransformError::InvalidJson {
+                line,
+                column,
+                offset,
+                ..
+            })) => {
+                assert_eq!((line, column), (2, 27));
+                assert_eq!(&json[offset..offset + 1], "}");
+            }
+            other => panic!("expected InvalidJson, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_array_writer_matches_serde_json() {
+        let stations = process_feed(FEED).unwrap().stations;
+        for pretty in [false, true] {
+            let mut writer = JsonArrayWriter::new(Vec::new(), pretty);
+            for station in &stations {
+                writer.write(station).unwrap();
+            }
+            let output = String::from_utf8(writer.finish().unwrap()).unwrap();
+            let expected = if pretty {
+                serde_json::to_string_pretty(&stations)
+            } else {
+                serde_json::to_string(&stations)
+            };
+            assert_eq!(output, expected.unwrap());
+        }
+        let empty: Vec<StationPriceLastUpdated> = Vec::new();
+        let writer = JsonArrayWriter::new(Vec::new(), true);
+        assert_eq!(
+            writer.finish().unwrap(),
+            serde_json::to_string_pretty(&empty).unwrap().as_bytes()
+        );
+    }
+}
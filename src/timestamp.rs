@@ -0,0 +1,376 @@
+use chrono::offset::LocalResult;
+use chrono::{DateTime, NaiveDateTime, Offset, ParseError, TimeDelta, TimeZone, Utc};
+use std::fmt;
+
+use chrono_tz::Tz;
+use thiserror::Error;
+
+/// How to resolve a local time that occurs twice, when the clocks go back
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AmbiguousTime {
+    /// Use the first occurrence (still on summer time)
+    #[default]
+    Earliest,
+    /// Use the second occurrence (already on standard time)
+    Latest,
+    /// Fail with `TimestampError::AmbiguousLocalTime`
+    Reject,
+}
+
+/// How to resolve a local time that never occurs, when the clocks go forward
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NonexistentTime {
+    /// Read the time with the offset in force just before the clocks changed,
+    /// e.g. 01:30 on the last Sunday of March in London becomes 01:30 UTC
+    #[default]
+    OffsetBeforeGap,
+    /// Fail with `TimestampError::NonexistentLocalTime`
+    Reject,
+}
+
+/// The timezone feed timestamps are published in, and how DST transitions are handled
+///
+/// Defaults to `Europe/London`, since UK retailers publish local time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimezoneOptions {
+    pub timezone: Tz,
+    pub ambiguous: AmbiguousTime,
+    pub nonexistent: NonexistentTime,
+}
+
+impl Default for TimezoneOptions {
+    fn default() -> Self {
+        TimezoneOptions {
+            timezone: chrono_tz::Europe::London,
+            ambiguous: AmbiguousTime::default(),
+            nonexistent: NonexistentTime::default(),
+        }
+    }
+}
+
+impl TimezoneOptions {
+    /// Default DST handling in the given timezone
+    pub fn in_timezone(timezone: Tz) -> Self {
+        TimezoneOptions {
+            timezone,
+            ..TimezoneOptions::default()
+        }
+    }
+}
+
+/// A format a feed's `last_updated` timestamp may be published in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TimestampFormat {
+    /// RFC 3339 / ISO 8601 with an explicit offset, e.g. "2024-11-27T11:45:32Z";
+    /// the timezone setting is not used
+    Rfc3339,
+    /// A `chrono` strftime pattern for a local date and time, read in the
+    /// configured timezone
+    Local(String),
+}
+
+impl TimestampFormat {
+    /// The formats tried by default, in order
+    ///
+    /// All patterns put the day before the month, so none of them can
+    /// disagree about a date.
+    pub fn defaults() -> Vec<TimestampFormat> {
+        let mut formats: Vec<TimestampFormat> = [
+            "%d/%m/%Y %H:%M:%S",
+            "%d/%m/%Y %H:%M",
+            "%d-%m-%Y %H:%M:%S",
+            "%d-%m-%Y %H:%M",
+        ]
+        .into_iter()
+        .map(|pattern| TimestampFormat::Local(pattern.to_string()))
+        .collect();
+        formats.push(TimestampFormat::Rfc3339);
+        formats.extend(
+            [
+                "%Y-%m-%dT%H:%M:%S%.f",
+                "%Y-%m-%d %H:%M:%S%.f",
+                "%Y-%m-%dT%H:%M",
+                "%Y-%m-%d %H:%M",
+            ]
+            .into_iter()
+            .map(|pattern| TimestampFormat::Local(pattern.to_string())),
+        );
+        formats
+    }
+
+    fn parse(
+        &self,
+        value: &str,
+        timezone: &TimezoneOptions,
+    ) -> Option<Result<DateTime<Utc>, TimestampError>> {
+        match self {
+            TimestampFormat::Rfc3339 => DateTime::parse_from_rfc3339(value)
+                .ok()
+                .map(|dt| Ok(dt.with_timezone(&Utc))),
+            TimestampFormat::Local(pattern) => NaiveDateTime::parse_from_str(value, pattern)
+                .ok()
+                .map(|local| local_to_utc(local, timezone)),
+        }
+    }
+}
+
+impl fmt::Display for TimestampFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimestampFormat::Rfc3339 => f.write_str("RFC 3339"),
+            TimestampFormat::Local(pattern) => f.write_str(pattern),
+        }
+    }
+}
+
+/// Errors that can occur while parsing a feed timestamp
+#[derive(Debug, Error)]
+pub enum TimestampError {
+    /// The timestamp does not match the expected format
+    #[error("{0}")]
+    Parse(#[from] ParseError),
+
+    /// The local time falls in the hour skipped when the clocks go forward
+    #[error("{local} does not exist in {timezone} (clocks went forward)")]
+    NonexistentLocalTime { local: NaiveDateTime, timezone: Tz },
+
+    /// The local time falls in the hour repeated when the clocks go back
+    #[error("{local} is ambiguous in {timezone} (clocks went back)")]
+    AmbiguousLocalTime { local: NaiveDateTime, timezone: Tz },
+
+    /// The timestamp matches none of the configured formats
+    #[error("matches none of the {formats} configured timestamp formats")]
+    UnrecognisedFormat { formats: usize },
+
+    /// The timestamp matches two formats that read it as different times,
+    /// typically because they order the day and month differently
+    #[error(
+        "ambiguous: \"{first}\" reads it as {first_reading}, \"{second}\" as {second_reading}"
+    )]
+    AmbiguousFormat {
+        first: TimestampFormat,
+        first_reading: DateTime<Utc>,
+        second: TimestampFormat,
+        second_reading: DateTime<Utc>,
+    },
+}
+
+/// Parses a timestamp with the first of `formats` that matches.
+///
+/// # Returns
+///
+/// The UTC datetime and the format that matched
+///
+/// # Errors
+///
+/// - `TimestampError::UnrecognisedFormat` if no format matches
+/// - `TimestampError::AmbiguousFormat` if a later format also matches but
+///   reads a different time, e.g. "05/06/2024" with both day-first and
+///   month-first patterns configured
+/// - DST errors from [`local_to_utc`] for the matching format
+///
+/// # Examples
+///
+/// ```rust
+/// use refuel_radar_transform::timestamp::{parse_timestamp, TimestampFormat, TimezoneOptions};
+///
+/// let formats = TimestampFormat::defaults();
+/// let (utc, format) =
+///     parse_timestamp("27-11-2024 11:45", &formats, &TimezoneOptions::default()).unwrap();
+/// assert_eq!(utc.to_rfc3339(), "2024-11-27T11:45:00+00:00");
+/// assert_eq!(format.to_string(), "%d-%m-%Y %H:%M");
+/// ```
+pub fn parse_timestamp<'a>(
+    value: &str,
+    formats: &'a [TimestampFormat],
+    timezone: &TimezoneOptions,
+) -> Result<(DateTime<Utc>, &'a TimestampFormat), TimestampError> {
+    let value = value.trim();
+    let mut matches = formats
+        .iter()
+        .filter_map(|format| format.parse(value, timezone).map(|result| (format, result)));
+
+    let Some((format, result)) = matches.next() else {
+        return Err(TimestampError::UnrecognisedFormat {
+            formats: formats.len(),
+        });
+    };
+    let reading = result?;
+
+    for (other, other_result) in matches {
+        if let Ok(other_reading) = other_result {
+            if other_reading != reading {
+                return Err(TimestampError::AmbiguousFormat {
+                    first: format.clone(),
+                    first_reading: reading,
+                    second: other.clone(),
+                    second_reading: other_reading,
+                });
+            }
+        }
+    }
+
+    Ok((reading, format))
+}
+
+/// Converts a local date and time in `options.timezone` to UTC.
+///
+/// # Errors
+///
+/// Returns a `TimestampError` if the time falls in a DST transition and the
+/// matching policy in `options` is `Reject`
+pub fn local_to_utc(
+    local: NaiveDateTime,
+    options: &TimezoneOptions,
+) -> Result<DateTime<Utc>, TimestampError> {
+    let timezone = options.timezone;
+    match timezone.from_local_datetime(&local) {
+        LocalResult::Single(dt) => Ok(dt.with_timezone(&Utc)),
+        LocalResult::Ambiguous(earliest, latest) => match options.ambiguous {
+            AmbiguousTime::Earliest => Ok(earliest.with_timezone(&Utc)),
+            AmbiguousTime::Latest => Ok(latest.with_timezone(&Utc)),
+            AmbiguousTime::Reject => Err(TimestampError::AmbiguousLocalTime { local, timezone }),
+        },
+        LocalResult::None => match options.nonexistent {
+            NonexistentTime::OffsetBeforeGap => {
+                // The offset in force before the gap is the one an hour earlier, which
+                // covers every real-world transition of up to an hour
+                let before = local - TimeDelta::hours(1);
+                let offset = timezone
+                    .offset_from_local_datetime(&before)
+                    .earliest()
+                    .ok_or(TimestampError::NonexistentLocalTime { local, timezone })?;
+                Ok(DateTime::from_naive_utc_and_offset(
+                    local - offset.fix(),
+                    Utc,
+                ))
+            }
+            NonexistentTime::Reject => {
+                Err(TimestampError::NonexistentLocalTime { local, timezone })
+            }
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn local(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    fn utc(s: &str, options: &TimezoneOptions) -> String {
+        local_to_utc(local(s), options).unwrap().to_rfc3339()
+    }
+
+    #[test]
+    fn test_london_offsets() {
+        let options = TimezoneOptions::default();
+        assert_eq!(
+            utc("2024-11-27 11:45:32", &options),
+            "2024-11-27T11:45:32+00:00"
+        );
+        assert_eq!(
+            utc("2024-07-01 12:00:00", &options),
+            "2024-07-01T11:00:00+00:00"
+        );
+    }
+
+    #[test]
+    fn test_dst_gap() {
+        let mut options = TimezoneOptions::default();
+        assert_eq!(
+            utc("2024-03-31 01:30:00", &options),
+            "2024-03-31T01:30:00+00:00"
+        );
+
+        options.nonexistent = NonexistentTime::Reject;
+        assert!(matches!(
+            local_to_utc(local("2024-03-31 01:30:00"), &options),
+            Err(TimestampError::NonexistentLocalTime { .. })
+        ));
+    }
+
+    #[test]
+    fn test_dst_repeated_hour() {
+        let mut options = TimezoneOptions::default();
+        assert_eq!(
+            utc("2024-10-27 01:30:00", &options),
+            "2024-10-27T00:30:00+00:00"
+        );
+
+        options.ambiguous = AmbiguousTime::Latest;
+        assert_eq!(
+            utc("2024-10-27 01:30:00", &options),
+            "2024-10-27T01:30:00+00:00"
+        );
+
+        options.ambiguous = AmbiguousTime::Reject;
+        assert!(matches!(
+            local_to_utc(local("2024-10-27 01:30:00"), &options),
+            Err(TimestampError::AmbiguousLocalTime { .. })
+        ));
+    }
+
+    #[test]
+    fn test_multiple_formats() {
+        let formats = TimestampFormat::defaults();
+        let options = TimezoneOptions::default();
+        let parse = |value| {
+            parse_timestamp(value, &formats, &options)
+                .map(|(utc, format)| (utc.to_rfc3339(), format.to_string()))
+                .unwrap()
+        };
+
+        assert_eq!(
+            parse("27/11/2024 11:45"),
+            (
+                "2024-11-27T11:45:00+00:00".to_string(),
+                "%d/%m/%Y %H:%M".to_string()
+            )
+        );
+        assert_eq!(
+            parse("2024-07-01T12:00:00+01:00"),
+            (
+                "2024-07-01T11:00:00+00:00".to_string(),
+                "RFC 3339".to_string()
+            )
+        );
+        assert_eq!(
+            parse("2024-07-01T12:00:00"),
+            (
+                "2024-07-01T11:00:00+00:00".to_string(),
+                "%Y-%m-%dT%H:%M:%S%.f".to_string()
+            )
+        );
+        assert!(matches!(
+            parse_timestamp("yesterday", &formats, &options),
+            Err(TimestampError::UnrecognisedFormat { .. })
+        ));
+    }
+
+    #[test]
+    fn test_ambiguous_day_month_order() {
+        let formats = [
+            TimestampFormat::Local("%d/%m/%Y %H:%M:%S".to_string()),
+            TimestampFormat::Local("%m/%d/%Y %H:%M:%S".to_string()),
+        ];
+        let options = TimezoneOptions::in_timezone(chrono_tz::UTC);
+        assert!(matches!(
+            parse_timestamp("05/06/2024 10:00:00", &formats, &options),
+            Err(TimestampError::AmbiguousFormat { .. })
+        ));
+        assert!(parse_timestamp("27/11/2024 10:00:00", &formats, &options).is_ok());
+        assert!(parse_timestamp("05/05/2024 10:00:00", &formats, &options).is_ok());
+    }
+
+    #[test]
+    fn test_timezone_override() {
+        let options = TimezoneOptions::in_timezone(chrono_tz::UTC);
+        assert_eq!(
+            utc("2024-07-01 12:00:00", &options),
+            "2024-07-01T12:00:00+00:00"
+        );
+    }
+}
@@ -0,0 +1,254 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::Serialize;
+
+use crate::fuel::FuelType;
+use crate::report::FeedReport;
+use crate::station_struts::StationPrices;
+
+/// The unit a feed publishes its prices in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+pub enum PriceUnit {
+    /// Pence per litre, e.g. 138.9
+    Pence,
+    /// Pounds per litre, e.g. 1.389
+    Pounds,
+    /// Tenths of a penny per litre, e.g. 1389
+    TenthsOfPenny,
+}
+
+impl PriceUnit {
+    const ALL: [PriceUnit; 3] = [
+        PriceUnit::Pence,
+        PriceUnit::Pounds,
+        PriceUnit::TenthsOfPenny,
+    ];
+
+    /// Converts a price in this unit to pence
+    pub fn to_pence(self, value: f64) -> f64 {
+        match self {
+            PriceUnit::Pence => value,
+            PriceUnit::Pounds => value * 100.0,
+            PriceUnit::TenthsOfPenny => value / 10.0,
+        }
+    }
+}
+
+/// An inclusive range of plausible prices, in pence per litre
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlausibleRange {
+    pub min: f64,
+    pub max: f64,
+}
+
+impl PlausibleRange {
+    pub fn contains(&self, pence: f64) -> bool {
+        (self.min..=self.max).contains(&pence)
+    }
+}
+
+/// Whether price units are detected for each station or once for a whole feed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UnitDetection {
+    /// Each station's prices may be in a different unit
+    #[default]
+    PerStation,
+    /// Every station in a feed uses the unit most of the feed's prices are in
+    PerFeed,
+}
+
+/// Settings for detecting price units and normalising prices to pence
+///
+/// # Defaults
+///
+/// - Per-station detection
+/// - Plausible ranges of 90–250p for E5, E10 and B7, 90–280p for SDV and
+///   30–300p for any other fuel
+#[derive(Debug, Clone, PartialEq)]
+pub struct PriceUnitOptions {
+    pub detection: UnitDetection,
+    /// Plausible range for each recognised fuel type
+    pub ranges: HashMap<FuelType, PlausibleRange>,
+    /// Plausible range for fuel types without an entry in `ranges`
+    pub default_range: PlausibleRange,
+}
+
+impl Default for PriceUnitOptions {
+    fn default() -> Self {
+        let petrol_and_diesel = PlausibleRange {
+            min: 90.0,
+            max: 250.0,
+        };
+        PriceUnitOptions {
+            detection: UnitDetection::default(),
+            ranges: HashMap::from([
+                (FuelType::E5, petrol_and_diesel),
+                (FuelType::E10, petrol_and_diesel),
+                (FuelType::B7, petrol_and_diesel),
+                (
+                    FuelType::Sdv,
+                    PlausibleRange {
+                        min: 90.0,
+                        max: 280.0,
+                    },
+                ),
+            ]),
+            default_range: PlausibleRange {
+                min: 30.0,
+                max: 300.0,
+            },
+        }
+    }
+}
+
+impl PriceUnitOptions {
+    fn range(&self, fuel_type: &FuelType) -> PlausibleRange {
+        self.ranges
+            .get(fuel_type)
+            .copied()
+            .unwrap_or(self.default_range)
+    }
+
+    /// The only unit that puts `value` inside the plausible range for `fuel_type`, if any
+    fn classify(&self, fuel_type: &FuelType, value: f64) -> Option<PriceUnit> {
+        let range = self.range(fuel_type);
+        let mut matching = PriceUnit::ALL
+            .into_iter()
+            .filter(|unit| range.contains(unit.to_pence(value)));
+        match (matching.next(), matching.next()) {
+            (Some(unit), None) => Some(unit),
+            _ => None,
+        }
+    }
+}
+
+/// Why a price was removed during unit normalisation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum PriceFlagReason {
+    /// No unit (or more than one) puts the price in the plausible range
+    Unclassifiable,
+    /// The price is implausible in the unit detected for its station or feed
+    OutOfRange,
+}
+
+/// A price that could not be confidently normalised to pence
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PriceFlag {
+    pub site_id: String,
+    pub fuel_type: FuelType,
+    /// The price as published in the feed
+    pub value: f64,
+    pub reason: PriceFlagReason,
+}
+
+/// Detects price units and normalises every price to pence with one decimal.
+///
+/// # Detection
+///
+/// - Each price is classified as the single unit that makes it plausible for
+///   its fuel type
+/// - The unit for a station (or feed, with [`UnitDetection::PerFeed`]) is the
+///   one most of its classifiable prices are in
+/// - Prices that cannot be classified, or that are implausible once converted,
+///   are removed and recorded in `report.price_flags`
+pub(crate) fn normalise_price_units(
+    stations: &mut [StationPrices],
+    options: &PriceUnitOptions,
+    report: &mut FeedReport,
+) {
+    let feed_unit = match options.detection {
+        UnitDetection::PerFeed => majority_unit(
+            stations
+                .iter()
+                .flat_map(|station| station_units(station, options)),
+        ),
+        UnitDetection::PerStation => None,
+    };
+
+    for station in stations {
+        let unit = match options.detection {
+            UnitDetection::PerFeed => feed_unit,
+            UnitDetection::PerStation => majority_unit(station_units(station, options)),
+        };
+        if let Some(unit) = unit {
+            *report.price_units.entry(unit).or_default() += 1;
+        }
+
+        let site_id = &station.site_id;
+        let first_flag = report.price_flags.len();
+        station.prices.retain(|fuel_type, value| {
+            let reason = match unit {
+                None => PriceFlagReason::Unclassifiable,
+                Some(unit) => {
+                    let pence = unit.to_pence(*value);
+                    if options.range(fuel_type).contains(pence) {
+                        *value = (pence * 10.0).round() / 10.0;
+                        return true;
+                    }
+                    PriceFlagReason::OutOfRange
+                }
+            };
+            report.price_flags.push(PriceFlag {
+                site_id: site_id.clone(),
+                fuel_type: fuel_type.clone(),
+                value: *value,
+                reason,
+            });
+            false
+        });
+        report.price_flags[first_flag..].sort_by(|a, b| a.fuel_type.cmp(&b.fuel_type));
+    }
+}
+
+fn station_units<'a>(
+    station: &'a StationPrices,
+    options: &'a PriceUnitOptions,
+) -> impl Iterator<Item = PriceUnit> + 'a {
+    station
+        .prices
+        .iter()
+        .filter_map(|(fuel_type, &value)| options.classify(fuel_type, value))
+}
+
+/// The most common unit, preferring pence, then pounds, on a tie
+fn majority_unit(units: impl Iterator<Item = PriceUnit>) -> Option<PriceUnit> {
+    let mut counts: BTreeMap<PriceUnit, usize> = BTreeMap::new();
+    for unit in units {
+        *counts.entry(unit).or_default() += 1;
+    }
+    counts
+        .into_iter()
+        .rev()
+        .max_by_key(|&(_, count)| count)
+        .map(|(unit, _)| unit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        let options = PriceUnitOptions::default();
+        assert_eq!(
+            options.classify(&FuelType::E10, 138.9),
+            Some(PriceUnit::Pence)
+        );
+        assert_eq!(
+            options.classify(&FuelType::E10, 1.389),
+            Some(PriceUnit::Pounds)
+        );
+        assert_eq!(
+            options.classify(&FuelType::E10, 1389.0),
+            Some(PriceUnit::TenthsOfPenny)
+        );
+        assert_eq!(options.classify(&FuelType::E10, 50.0), None);
+    }
+
+    #[test]
+    fn test_majority_prefers_pence_on_tie() {
+        let units = [PriceUnit::Pounds, PriceUnit::Pence];
+        assert_eq!(majority_unit(units.into_iter()), Some(PriceUnit::Pence));
+        assert_eq!(majority_unit(std::iter::empty()), None);
+    }
+}